@@ -1,6 +1,10 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use color_eyre::Result;
 
-use crate::config::{get_config_dir, get_data_dir};
+use crate::{
+    config::{get_config_dir, get_data_dir},
+    repositories::apt::lint::{self, Severity},
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version = version(), about)]
@@ -12,6 +16,40 @@ pub struct Cli {
     /// Frame rate, i.e. number of frames per second
     #[arg(short, long, value_name = "FLOAT", default_value_t = 60.0)]
     pub frame_rate: f64,
+
+    /// Lint the apt source files, print the problems found and exit
+    #[arg(long)]
+    pub lint: bool,
+
+    /// How --lint prints the problems
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, requires = "lint")]
+    pub format: OutputFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// `file:line: severity: message [check]`, one per line
+    Text,
+    /// A JSON array of objects with path, line, severity, code and message
+    Json,
+}
+
+/// Prints the lint diagnostics of the apt sources. Returns the exit code: 1 when apt would
+/// refuse something, 0 otherwise.
+pub fn run_lint(format: OutputFormat) -> Result<i32> {
+    let diagnostics = lint::lint();
+    match format {
+        OutputFormat::Text => {
+            for diagnostic in &diagnostics {
+                println!("{diagnostic}");
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diagnostics)?),
+    }
+    let has_errors = diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error);
+    Ok(has_errors as i32)
 }
 
 const VERSION_MESSAGE: &str = concat!(
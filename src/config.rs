@@ -11,7 +11,7 @@ use ratatui::style::{Color, Modifier, Style};
 use serde::{de::Deserializer, Deserialize};
 use tracing::error;
 
-use crate::{ui::action::Action, ui::Mode};
+use crate::{repositories::apt::SourceFormat, ui::action::Action, ui::Mode};
 
 const CONFIG: &str = include_str!("../.config/config.json5");
 
@@ -23,11 +23,34 @@ pub struct AppConfig {
     pub config_dir: PathBuf,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct AptConfig {
+    /// Format used for files created in `sources.list.d`.
+    #[serde(default = "AptConfig::default_source_format")]
+    pub new_source_format: SourceFormat,
+}
+
+impl AptConfig {
+    fn default_source_format() -> SourceFormat {
+        SourceFormat::Deb822
+    }
+}
+
+impl Default for AptConfig {
+    fn default() -> Self {
+        Self {
+            new_source_format: Self::default_source_format(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct Config {
     #[serde(default, flatten)]
     pub config: AppConfig,
     #[serde(default)]
+    pub apt: AptConfig,
+    #[serde(default)]
     pub keybindings: KeyBindings,
     #[serde(default)]
     pub styles: Styles,
@@ -505,7 +528,7 @@ mod tests {
         let c = Config::new()?;
         assert_eq!(
             c.keybindings
-                .get(&Mode::System)
+                .get(&Mode::Universal)
                 .unwrap()
                 .get(&parse_key_sequence("<q>").unwrap_or_default())
                 .unwrap(),
@@ -514,6 +537,22 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_text_input_keys() -> Result<()> {
+        let c = Config::new()?;
+        let text_input = c.keybindings.get(&Mode::TextInput).unwrap();
+        assert_eq!(
+            text_input.get(&parse_key_sequence("<esc>").unwrap_or_default()),
+            Some(&Action::Cancel)
+        );
+        assert_eq!(
+            text_input.get(&parse_key_sequence("<q>").unwrap_or_default()),
+            None
+        );
+        assert_eq!(c.apt.new_source_format, SourceFormat::Deb822);
+        Ok(())
+    }
+
     #[test]
     fn test_simple_keys() {
         assert_eq!(
@@ -17,6 +17,9 @@ async fn main() -> Result<()> {
     crate::logging::init()?;
 
     let args = Cli::parse();
+    if args.lint {
+        std::process::exit(cli::run_lint(args.format)?);
+    }
     let mut app = App::new(args.tick_rate, args.frame_rate)?;
     app.run().await?;
     Ok(())
@@ -1,100 +1,641 @@
-use color_eyre::Result;
-use regex::Regex;
-use std::fs::File;
-use std::io::{self, BufRead};
+use color_eyre::{eyre::eyre, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
 use std::path::Path;
 use std::{fs, path::PathBuf};
+use tracing::warn;
 
 use super::Repository;
 
-#[derive(Debug, Default)]
+pub(crate) mod apt_conf;
+pub(crate) mod auth;
+pub(crate) mod components;
+pub(crate) mod convert;
+pub(crate) mod deb822;
+pub(crate) mod dpkg;
+pub(crate) mod duplicates;
+pub(crate) mod edit;
+pub(crate) mod fixes;
+pub(crate) mod keyrings;
+pub(crate) mod legacy_keys;
+pub(crate) mod lint;
+pub(crate) mod lists;
+pub(crate) mod new_source;
+pub(crate) mod one_line;
+pub(crate) mod openpgp;
+pub(crate) mod permissions;
+pub(crate) mod ppa;
+pub(crate) mod preferences;
+pub(crate) mod release;
+pub(crate) mod removal;
+pub(crate) mod rewrite;
+pub(crate) mod unattended;
+pub(crate) mod vendors;
+
+use deb822::Deb822Stanza;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SourceType {
+    #[default]
+    Deb,
+    DebSrc,
+}
+
+impl SourceType {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "deb" => Some(Self::Deb),
+            "deb-src" => Some(Self::DebSrc),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deb => write!(f, "deb"),
+            Self::DebSrc => write!(f, "deb-src"),
+        }
+    }
+}
+
+/// A single source apt will fetch from: one line of a `.list` file, or one type/URI/suite
+/// combination of a deb822 stanza.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct AptSource {
-    pub _enabled: bool,
+    pub enabled: bool,
+    pub source_type: SourceType,
+    /// The bracketed `key=value` options, using the one-line names (`arch`, `signed-by`, ...)
+    /// for deb822 fields as well.
+    pub options: Vec<(String, String)>,
     pub uri: String,
+    pub suite: String,
+    pub components: Vec<String>,
+    /// 1-based line the entry starts on in its file.
+    pub line_number: usize,
+    /// The entry exactly as written, the whole stanza for deb822 files.
+    pub original: String,
+}
+
+impl AptSource {
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The keyring file from `signed-by`. Inline keys and fingerprints are not files, so they
+    /// are left out.
+    pub fn signed_by_path(&self) -> Option<&str> {
+        self.option("signed-by")
+            .filter(|value| value.starts_with('/'))
+    }
+
+    /// Options as they would appear between the brackets of a one-line entry. Inline keys
+    /// from deb822 files are shortened so they fit on a line.
+    pub fn options_display(&self) -> String {
+        self.options
+            .iter()
+            .map(|(key, value)| {
+                if value.contains('\n') {
+                    format!("{key}=<inline key>")
+                } else {
+                    format!("{key}={value}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for AptSource {
+    /// The one-line form of the source, without the leading `#` of disabled entries and with
+    /// the password in the URI masked.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source_type)?;
+        if !self.options.is_empty() {
+            write!(f, " [{}]", self.options_display())?;
+        }
+        write!(f, " {} {}", self.masked_uri(), self.suite)?;
+        for component in &self.components {
+            write!(f, " {component}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The two file formats apt reads sources from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceFormat {
+    /// `.list` files (and `sources.list`), one `deb ...` entry per line.
+    #[default]
+    OneLine,
+    /// `.sources` files made of deb822 stanzas.
+    Deb822,
+}
+
+impl SourceFormat {
+    /// The format apt uses for a file in `sources.list.d`, or `None` for the extensions apt
+    /// ignores.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("list") => Some(Self::OneLine),
+            Some("sources") => Some(Self::Deb822),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct AptList {
     pub file_path: PathBuf,
+    pub format: SourceFormat,
+    /// Set for `/etc/apt/sources.list`, the main list most distributions ship their archive
+    /// entries in. Everything else comes from `sources.list.d`.
+    pub primary: bool,
     pub sources: Vec<AptSource>,
 }
 
+impl AptList {
+    /// Short name used when showing where a source comes from.
+    pub fn display_name(&self) -> String {
+        let file_name = self
+            .file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if self.primary {
+            format!("{file_name} (primary)")
+        } else {
+            file_name
+        }
+    }
+}
+
 const APT_SOURCES_LIST_D_PATH: &str = "/etc/apt/sources.list.d";
-const _APT_SOURCES_LIST_PATH: &str = "/etc/apt/sources.list";
+const APT_SOURCES_LIST_PATH: &str = "/etc/apt/sources.list";
+const APT_CONF_PATH: &str = "/etc/apt/apt.conf";
+const APT_CONF_D_PATH: &str = "/etc/apt/apt.conf.d";
+const APT_PREFERENCES_PATH: &str = "/etc/apt/preferences";
+const APT_PREFERENCES_D_PATH: &str = "/etc/apt/preferences.d";
+
+/// The content of `path`, with the bytes that aren't UTF-8 replaced, as apt doesn't require
+/// its files to be UTF-8.
+pub(crate) fn read_lossy(path: &Path) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
 
-fn read_lines<P>(filename: P) -> Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+/// Where a loaded source is: the index of its list in `AptRepositories::items` and of the
+/// source within that list.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceRef {
+    pub list: usize,
+    pub source: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct AptRepositories {
     pub initialized: bool,
     pub items: Vec<AptList>,
+    /// Source files left out in whole or in part because they didn't parse or couldn't be
+    /// read. apt may still read what was left out.
+    pub skipped: Vec<PathBuf>,
+    /// Where `sources.list` and `sources.list.d` are read from instead of `/etc/apt`, for
+    /// tests.
+    apt_dir: Option<PathBuf>,
 }
 
-impl Default for AptRepositories {
-    fn default() -> Self {
-        Self {
-            initialized: false,
-            items: Vec::default(),
+/// deb822 field names that differ from the matching one-line option.
+const DEB822_OPTION_NAMES: [(&str, &str); 3] = [
+    ("Architectures", "arch"),
+    ("Languages", "lang"),
+    ("Targets", "target"),
+];
+
+/// Fields that describe the entry itself rather than an option.
+const DEB822_ENTRY_FIELDS: [&str; 5] = ["Types", "URIs", "Suites", "Components", "Enabled"];
+
+/// Expands a deb822 stanza into one entry per type, URI and suite, the same way apt does.
+fn sources_from_stanza(stanza: &Deb822Stanza, original: &str) -> Vec<AptSource> {
+    let enabled = stanza
+        .get("Enabled")
+        .map(|value| !value.eq_ignore_ascii_case("no"))
+        .unwrap_or(true);
+
+    let options: Vec<(String, String)> = stanza
+        .fields
+        .iter()
+        .filter(|(key, _)| {
+            !DEB822_ENTRY_FIELDS
+                .iter()
+                .any(|field| field.eq_ignore_ascii_case(key))
+        })
+        .map(|(key, value)| {
+            let name = DEB822_OPTION_NAMES
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(key))
+                .map_or_else(|| key.to_ascii_lowercase(), |(_, name)| name.to_string());
+            // Multi-valued options are comma separated in the one-line format, but an inline
+            // key has to keep its line breaks.
+            let value = if value.contains('\n') {
+                value.clone()
+            } else {
+                value.split_whitespace().collect::<Vec<_>>().join(",")
+            };
+            (name, value)
+        })
+        .collect();
+    let components = stanza.get_list("Components");
+
+    let mut sources = Vec::new();
+    for source_type in stanza.get_list("Types") {
+        let Some(source_type) = SourceType::parse(&source_type) else {
+            continue;
+        };
+        for uri in stanza.get_list("URIs") {
+            for suite in stanza.get_list("Suites") {
+                sources.push(AptSource {
+                    enabled,
+                    source_type,
+                    options: options.clone(),
+                    uri: uri.clone(),
+                    suite,
+                    components: components.clone(),
+                    line_number: stanza.line_number,
+                    original: original.to_string(),
+                });
+            }
         }
     }
+    sources
+}
+
+/// The sources of the deb822 file `content`.
+fn sources_in_deb822(content: &str) -> Result<Vec<AptSource>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut sources = Vec::new();
+    for stanza in deb822::parse_stanzas(content)? {
+        let start = stanza.line_number - 1;
+        let original = lines[start..start + stanza.line_count].join("\n");
+        sources.extend(sources_from_stanza(&stanza, &original));
+    }
+    Ok(sources)
+}
+
+/// The sources of `content`, a file in `format`. One-line entries that don't parse are left
+/// out, like apt leaves them out.
+pub fn sources_in_content(content: &str, format: SourceFormat) -> Result<Vec<AptSource>> {
+    match format {
+        SourceFormat::Deb822 => sources_in_deb822(content),
+        SourceFormat::OneLine => Ok(content
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| one_line::parse_line(line, index + 1).ok().flatten())
+            .collect()),
+    }
 }
 
 impl AptRepositories {
-    pub fn new() -> Self {
-        Self::default()
+    /// Adds `apt_list` with the sources of its file. Files that can't be read are skipped
+    /// with a warning, the linter reports them. Like apt, bytes that aren't UTF-8 don't stop
+    /// the file from being read.
+    fn load_list(&mut self, mut apt_list: AptList) {
+        let content = match read_lossy(&apt_list.file_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            Err(e) => {
+                warn!("Skipping {}: {e}", apt_list.file_path.display());
+                self.skipped.push(apt_list.file_path);
+                return;
+            }
+        };
+        match apt_list.format {
+            SourceFormat::Deb822 => match sources_in_deb822(&content) {
+                Ok(sources) => apt_list.sources = sources,
+                Err(e) => {
+                    warn!("Skipping {}: {e}", apt_list.file_path.display());
+                    self.skipped.push(apt_list.file_path);
+                    return;
+                }
+            },
+            SourceFormat::OneLine => {
+                let mut skipped_any = false;
+                for (index, line) in content.lines().enumerate() {
+                    match one_line::parse_line(line, index + 1) {
+                        Ok(Some(source)) => apt_list.sources.push(source),
+                        Ok(None) => {}
+                        Err(e) => {
+                            warn!("Skipping entry in {}: {e}", apt_list.file_path.display());
+                            skipped_any = true;
+                        }
+                    }
+                }
+                if skipped_any {
+                    self.skipped.push(apt_list.file_path.clone());
+                }
+            }
+        }
+        self.items.push(apt_list);
+    }
+
+    /// Flips the source at `index` (as counted by `sources`) between enabled and disabled and
+    /// reloads the lists. Returns whether the source is now enabled.
+    pub fn toggle_source(&mut self, index: usize) -> Result<bool> {
+        let Some((apt_list, source)) = self.sources().nth(index) else {
+            return Err(eyre!("No source at index {index}"));
+        };
+        let enabled = !source.enabled;
+        edit::set_enabled(apt_list, source, enabled)?;
+        self.load_repository_list()?;
+        Ok(enabled)
+    }
+
+    /// Disables the given sources, one write per file, and reloads the lists. Returns how many
+    /// sources were disabled, leaving out the ones that already were or don't exist.
+    pub fn disable_sources(&mut self, source_refs: &[SourceRef]) -> Result<usize> {
+        let mut by_list: BTreeMap<usize, Vec<&AptSource>> = BTreeMap::new();
+        for source_ref in source_refs {
+            if let Some((_, source)) = self.get(*source_ref).filter(|(_, source)| source.enabled) {
+                by_list.entry(source_ref.list).or_default().push(source);
+            }
+        }
+        for (list_index, sources) in &by_list {
+            edit::set_enabled_many(&self.items[*list_index], sources, false)?;
+        }
+        let count = by_list.values().map(Vec::len).sum();
+        if count > 0 {
+            self.load_repository_list()?;
+        }
+        Ok(count)
+    }
+
+    /// Position of the source at `index` (as counted by `sources`).
+    pub fn ref_of(&self, index: usize) -> Option<SourceRef> {
+        let mut remaining = index;
+        for (list_index, apt_list) in self.items.iter().enumerate() {
+            if remaining < apt_list.sources.len() {
+                return Some(SourceRef {
+                    list: list_index,
+                    source: remaining,
+                });
+            }
+            remaining -= apt_list.sources.len();
+        }
+        None
+    }
+
+    /// Index into `items` of the list the source at `index` (as counted by `sources`) is in.
+    pub fn list_index_of(&self, index: usize) -> Option<usize> {
+        self.ref_of(index).map(|source_ref| source_ref.list)
+    }
+
+    pub fn get(&self, source_ref: SourceRef) -> Option<(&AptList, &AptSource)> {
+        let apt_list = self.items.get(source_ref.list)?;
+        Some((apt_list, apt_list.sources.get(source_ref.source)?))
+    }
+
+    /// Like `sources`, with the position of each source.
+    pub fn source_refs(&self) -> impl Iterator<Item = (SourceRef, &AptList, &AptSource)> {
+        self.items
+            .iter()
+            .enumerate()
+            .flat_map(|(list_index, apt_list)| {
+                apt_list
+                    .sources
+                    .iter()
+                    .enumerate()
+                    .map(move |(source_index, source)| {
+                        (
+                            SourceRef {
+                                list: list_index,
+                                source: source_index,
+                            },
+                            apt_list,
+                            source,
+                        )
+                    })
+            })
+    }
+
+    /// Every loaded source along with the list it comes from, in the order apt reads them.
+    pub fn sources(&self) -> impl Iterator<Item = (&AptList, &AptSource)> {
+        self.items.iter().flat_map(|apt_list| {
+            apt_list
+                .sources
+                .iter()
+                .map(move |source| (apt_list, source))
+        })
     }
 }
 
 impl Repository for AptRepositories {
     fn check_for_repository(&self) -> bool {
+        if Path::new(APT_SOURCES_LIST_PATH).is_file() {
+            return true;
+        }
         let apt_d_dir = fs::read_dir(APT_SOURCES_LIST_D_PATH);
         match apt_d_dir {
-            Ok(apt_list) => !apt_list.count() > 0,
+            Ok(mut apt_list) => apt_list.next().is_some(),
             Err(_) => false,
         }
     }
 
     fn load_repository_list(&mut self) -> Result<i32> {
-        let apt_list_d = fs::read_dir(APT_SOURCES_LIST_D_PATH)?;
-        // TODO: include sources.list
-        let regex = Regex::new("^\\s{0,}#{0,}\\s{0,}deb").unwrap();
-        let mut total = 0;
-        for path in apt_list_d {
-            let mut apt_list = AptList::default();
-            let path = path?.path();
-            apt_list.file_path = path.clone();
-            if let Ok(lines) = read_lines(&path) {
-                let filtered_lines = lines.filter(|x| match x {
-                    Ok(line) => regex.is_match(line),
-                    Err(_) => false,
-                });
+        self.items.clear();
+        self.skipped.clear();
 
-                for line in filtered_lines {
-                    let line_str = line?;
-                    apt_list.sources.push(AptSource {
-                        uri: line_str.clone(), // going to eat the small perf cost here
-                        _enabled: !line_str.starts_with("#"),
-                    });
-                    total += 1;
-                }
+        let (sources_list, sources_list_d) = match &self.apt_dir {
+            Some(dir) => (dir.join("sources.list"), dir.join("sources.list.d")),
+            None => (
+                PathBuf::from(APT_SOURCES_LIST_PATH),
+                PathBuf::from(APT_SOURCES_LIST_D_PATH),
+            ),
+        };
 
-                self.items.push(apt_list);
-            }
+        // The main list is optional (newer releases ship everything in sources.list.d), but
+        // when it exists it is listed first, the same order apt reads it in.
+        let primary_list = AptList {
+            file_path: sources_list,
+            format: SourceFormat::OneLine,
+            primary: true,
+            ..AptList::default()
+        };
+        self.load_list(primary_list);
+
+        let mut paths = match fs::read_dir(&sources_list_d) {
+            Ok(apt_list_d) => apt_list_d
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        paths.sort();
+        for path in paths {
+            // apt ignores anything that isn't a .list or .sources file
+            let Some(format) = SourceFormat::from_path(&path) else {
+                continue;
+            };
+            let apt_list = AptList {
+                file_path: path,
+                format,
+                ..AptList::default()
+            };
+            self.load_list(apt_list);
         }
+
         self.initialized = true;
-        Ok(total)
+        Ok(self
+            .items
+            .iter()
+            .map(|apt_list| apt_list.sources.len() as i32)
+            .sum())
     }
 
     fn get_repository_list(&self) -> Vec<String> {
-        self.items
-            .iter()
-            .flat_map(|apt_list| apt_list.sources.iter().map(|source| source.uri.clone()))
+        self.sources()
+            .map(|(apt_list, source)| format!("{}: {source}", apt_list.display_name()))
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use test_support::TempDir;
+
+    #[test]
+    fn test_disable_sources() {
+        let dir = TempDir::new("disable");
+        fs::create_dir(dir.join("sources.list.d")).unwrap();
+        let path = dir.join("sources.list.d/vendor.list");
+        let content = "deb https://vendor.example/apt stable main\n# deb https://old.example/apt stable main\n";
+        fs::write(&path, content).unwrap();
+        let mut repositories = test_support::load(&dir);
+        let missing = SourceRef { list: 0, source: 5 };
+        let disabled = SourceRef { list: 0, source: 1 };
+        assert_eq!(
+            repositories.disable_sources(&[missing, disabled]).unwrap(),
+            0
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        let enabled = SourceRef { list: 0, source: 0 };
+        assert_eq!(
+            repositories
+                .disable_sources(&[enabled, disabled, missing])
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# deb https://vendor.example/apt stable main\n# deb https://old.example/apt stable main\n"
+        );
+        let (_, source) = repositories.get(enabled).unwrap();
+        assert!(!source.enabled);
+    }
+}
+
+/// Setup shared by the tests of the apt modules.
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::ops::Deref;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// A directory of its own for a test, removed when dropped, so also when the test panics.
+    pub struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub fn new(name: &str) -> Self {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "yacufu-{name}-{}-{}",
+                std::process::id(),
+                COUNT.fetch_add(1, Ordering::Relaxed)
+            ));
+            // Left behind by a run that was killed before it could clean up
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Builds repositories from the content of their files, without reading them.
+    #[derive(Default)]
+    pub struct RepositoriesBuilder {
+        items: Vec<AptList>,
+    }
+
+    impl RepositoriesBuilder {
+        /// Adds the list at `path`, in the format its extension stands for.
+        pub fn list(mut self, path: impl Into<PathBuf>, content: &str) -> Self {
+            let file_path = path.into();
+            let format = if file_path.extension().is_some_and(|ext| ext == "sources") {
+                SourceFormat::Deb822
+            } else {
+                SourceFormat::OneLine
+            };
+            self.items.push(AptList {
+                sources: sources_in_content(content, format).unwrap(),
+                file_path,
+                format,
+                ..AptList::default()
+            });
+            self
+        }
+
+        /// Adds a one-line list with one entry per item of `lines`.
+        pub fn lines(self, path: impl Into<PathBuf>, lines: &[&str]) -> Self {
+            self.list(path, &lines.join("\n"))
+        }
+
+        pub fn build(self) -> AptRepositories {
+            AptRepositories {
+                initialized: true,
+                items: self.items,
+                ..AptRepositories::default()
+            }
+        }
+    }
+
+    pub fn repositories() -> RepositoriesBuilder {
+        RepositoriesBuilder::default()
+    }
+
+    /// Loads the lists from `sources.list` and `sources.list.d` in `dir`, which is also where
+    /// they are loaded from again after every change.
+    pub fn load(dir: &Path) -> AptRepositories {
+        let mut repositories = AptRepositories {
+            apt_dir: Some(dir.to_path_buf()),
+            ..AptRepositories::default()
+        };
+        repositories.load_repository_list().unwrap();
+        repositories
+    }
+}
@@ -0,0 +1,595 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use super::{
+    edit::write_atomic,
+    lint::{is_silently_ignored, Diagnostic, Severity},
+    APT_CONF_D_PATH, APT_CONF_PATH,
+};
+
+/// A token of apt's configuration syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Open,
+    Close,
+    Semicolon,
+    /// `#clear`, which removes a key and everything under it.
+    Clear,
+    Include,
+}
+
+/// A token with the line it is on and where it is in the file.
+type Spanned = (Token, usize, Range<usize>);
+
+/// A value as a file sets it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValue {
+    pub path: PathBuf,
+    /// 1-based line of the value.
+    pub line: usize,
+    /// The full key, scopes joined with `::`, as the file spells it.
+    pub key: String,
+    pub value: String,
+    /// Whether the value is appended to a list rather than set, as with `"value";` in a scope.
+    pub list_item: bool,
+    /// Where the value is in the file, quotes included. `None` for a key without a value.
+    pub span: Option<Range<usize>>,
+}
+
+impl ConfigValue {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.path.display(), self.line)
+    }
+}
+
+/// What a statement of a configuration file does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigStatement {
+    Set(ConfigValue),
+    Clear(String),
+}
+
+/// Splits `content` into tokens with the line and byte range of each. Comments are `//`,
+/// `/* */` and `#` up to the end of the line, except for the `#clear` and `#include`
+/// directives.
+fn tokenize(content: &str) -> std::result::Result<Vec<Spanned>, (usize, String)> {
+    let bytes = content.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    let skip_line = |i: &mut usize| {
+        while *i < bytes.len() && bytes[*i] != b'\n' {
+            *i += 1;
+        }
+    };
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b'\n' => {
+                line += 1;
+                i += 1;
+            }
+            byte if byte.is_ascii_whitespace() => i += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => skip_line(&mut i),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let Some(end) = content[i + 2..].find("*/") else {
+                    return Err((line, "unterminated /* comment".to_string()));
+                };
+                line += content[i..i + 2 + end].matches('\n').count();
+                i += end + 4;
+            }
+            b'#' => {
+                let word_end = content[i + 1..]
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .map_or(bytes.len(), |end| i + 1 + end);
+                match &content[i + 1..word_end] {
+                    "clear" => tokens.push((Token::Clear, line, start..word_end)),
+                    "include" => tokens.push((Token::Include, line, start..word_end)),
+                    _ => {
+                        skip_line(&mut i);
+                        continue;
+                    }
+                }
+                i = word_end;
+            }
+            b'"' => {
+                let Some(end) = content[i + 1..].find(['"', '\n']) else {
+                    return Err((line, "unterminated quote".to_string()));
+                };
+                if bytes[i + 1 + end] == b'\n' {
+                    return Err((line, "unterminated quote".to_string()));
+                }
+                let value = content[i + 1..i + 1 + end].to_string();
+                i += end + 2;
+                tokens.push((Token::Quoted(value), line, start..i));
+            }
+            b'{' | b'}' | b';' => {
+                let token = match bytes[i] {
+                    b'{' => Token::Open,
+                    b'}' => Token::Close,
+                    _ => Token::Semicolon,
+                };
+                i += 1;
+                tokens.push((token, line, start..i));
+            }
+            _ => {
+                while i < bytes.len()
+                    && !bytes[i].is_ascii_whitespace()
+                    && !matches!(bytes[i], b'{' | b'}' | b';' | b'"')
+                    && !content[i..].starts_with("//")
+                {
+                    i += 1;
+                }
+                tokens.push((Token::Word(content[start..i].to_string()), line, start..i));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses a file of apt's configuration syntax, `Key "value";` statements in nested
+/// `Scope { ... };` blocks. apt refuses a file with a syntax error, so its statements are
+/// dropped and the error reported.
+pub fn parse_config(path: &Path, content: &str) -> (Vec<ConfigStatement>, Vec<Diagnostic>) {
+    let error = |line, message| Diagnostic {
+        path: path.to_path_buf(),
+        line,
+        severity: Severity::Error,
+        code: "syntax",
+        message,
+    };
+    let tokens = match tokenize(content) {
+        Ok(tokens) => tokens,
+        Err((line, message)) => return (Vec::new(), vec![error(Some(line), message)]),
+    };
+    let mut statements = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut scopes: Vec<String> = Vec::new();
+    let mut pending: Vec<Spanned> = Vec::new();
+    let full_key = |scopes: &[String], tag: &str| {
+        let mut parts: Vec<&str> = scopes.iter().map(String::as_str).collect();
+        if !tag.is_empty() {
+            parts.push(tag);
+        }
+        parts.join("::")
+    };
+    for (token, line, span) in tokens {
+        match token {
+            Token::Open => {
+                match pending.as_slice() {
+                    [(Token::Word(tag) | Token::Quoted(tag), _, _)] => scopes.push(tag.clone()),
+                    _ => {
+                        let message = "a block has to follow a single name".to_string();
+                        return (Vec::new(), vec![error(Some(line), message)]);
+                    }
+                }
+                pending.clear();
+            }
+            Token::Close if !pending.is_empty() => {
+                let message = "missing ; before }".to_string();
+                return (Vec::new(), vec![error(Some(line), message)]);
+            }
+            Token::Close => {
+                if scopes.pop().is_none() {
+                    let message = "} without a matching {".to_string();
+                    return (Vec::new(), vec![error(Some(line), message)]);
+                }
+            }
+            Token::Semicolon => {
+                let statement = std::mem::take(&mut pending);
+                match statement.as_slice() {
+                    [] => {}
+                    [(Token::Clear, _, _), (Token::Word(key) | Token::Quoted(key), _, _)] => {
+                        statements.push(ConfigStatement::Clear(full_key(&scopes, key)));
+                    }
+                    [(Token::Include, line, _), ..] => diagnostics.push(Diagnostic {
+                        path: path.to_path_buf(),
+                        line: Some(*line),
+                        severity: Severity::Info,
+                        code: "include",
+                        message: "#include isn't followed, its settings aren't shown".to_string(),
+                    }),
+                    [(Token::Quoted(value), line, span)] if !scopes.is_empty() => {
+                        statements.push(ConfigStatement::Set(ConfigValue {
+                            path: path.to_path_buf(),
+                            line: *line,
+                            key: full_key(&scopes, ""),
+                            value: value.clone(),
+                            list_item: true,
+                            span: Some(span.clone()),
+                        }));
+                    }
+                    [(Token::Word(tag), line, _)] => {
+                        statements.push(ConfigStatement::Set(ConfigValue {
+                            path: path.to_path_buf(),
+                            line: *line,
+                            key: full_key(&scopes, tag),
+                            value: String::new(),
+                            list_item: false,
+                            span: None,
+                        }));
+                    }
+                    [(Token::Word(tag) | Token::Quoted(tag), _, _), (Token::Word(value) | Token::Quoted(value), line, span)] =>
+                    {
+                        // `Key:: "value";` appends to the list `Key`
+                        let (tag, list_item) = match tag.strip_suffix("::") {
+                            Some(tag) => (tag, true),
+                            None => (tag.as_str(), false),
+                        };
+                        statements.push(ConfigStatement::Set(ConfigValue {
+                            path: path.to_path_buf(),
+                            line: *line,
+                            key: full_key(&scopes, tag),
+                            value: value.clone(),
+                            list_item,
+                            span: Some(span.clone()),
+                        }));
+                    }
+                    [(_, line, _), ..] => {
+                        let message = "expected a name and a value before ;".to_string();
+                        return (Vec::new(), vec![error(Some(*line), message)]);
+                    }
+                }
+            }
+            token => pending.push((token, line, span)),
+        }
+    }
+    if let Some((_, line, _)) = pending.first() {
+        return (
+            Vec::new(),
+            vec![error(Some(*line), "missing ;".to_string())],
+        );
+    }
+    if !scopes.is_empty() {
+        let message = format!("{} isn't closed with }}", scopes.join("::"));
+        return (Vec::new(), vec![error(None, message)]);
+    }
+    (statements, diagnostics)
+}
+
+/// A key of the merged configuration, with every value the files give it in the order apt
+/// reads them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub key: String,
+    pub definitions: Vec<ConfigValue>,
+}
+
+impl Setting {
+    pub fn is_list(&self) -> bool {
+        self.definitions
+            .iter()
+            .any(|definition| definition.list_item)
+    }
+
+    /// The definition apt uses: the last one read, for a list its last item.
+    pub fn effective(&self) -> &ConfigValue {
+        self.definitions.last().expect("settings have a definition")
+    }
+
+    pub fn values(&self) -> Vec<&str> {
+        self.definitions
+            .iter()
+            .filter(|definition| definition.list_item)
+            .map(|definition| definition.value.as_str())
+            .collect()
+    }
+
+    /// Whether files set the key to different values, so all but the last are overridden.
+    pub fn is_conflict(&self) -> bool {
+        !self.is_list()
+            && self
+                .definitions
+                .iter()
+                .any(|definition| definition.value != self.effective().value)
+    }
+}
+
+/// The configuration apt ends up with from `apt.conf.d` and `apt.conf`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AptConfig {
+    /// Sorted by key.
+    pub settings: Vec<Setting>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl AptConfig {
+    /// Applies `statements` in order. Keys are case insensitive.
+    pub fn merge(statements: Vec<ConfigStatement>, diagnostics: Vec<Diagnostic>) -> Self {
+        let mut settings: BTreeMap<String, Setting> = BTreeMap::new();
+        for statement in statements {
+            match statement {
+                ConfigStatement::Set(value) => settings
+                    .entry(value.key.to_ascii_lowercase())
+                    .or_insert_with(|| Setting {
+                        key: value.key.clone(),
+                        definitions: Vec::new(),
+                    })
+                    .definitions
+                    .push(value),
+                ConfigStatement::Clear(key) => {
+                    let key = key.to_ascii_lowercase();
+                    let prefix = format!("{key}::");
+                    settings.retain(|name, _| *name != key && !name.starts_with(&prefix));
+                }
+            }
+        }
+        Self {
+            settings: settings.into_values().collect(),
+            diagnostics,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Setting> {
+        self.settings
+            .iter()
+            .find(|setting| setting.key.eq_ignore_ascii_case(key))
+    }
+
+    /// The items of a list, empty when it isn't set.
+    pub fn list(&self, key: &str) -> Vec<&ConfigValue> {
+        self.get(key)
+            .map(|setting| {
+                setting
+                    .definitions
+                    .iter()
+                    .filter(|definition| definition.list_item)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn conflicts(&self) -> usize {
+        self.settings
+            .iter()
+            .filter(|setting| setting.is_conflict())
+            .count()
+    }
+}
+
+/// Whether apt reads the file `name` of `apt.conf.d`: no extension or `.conf`, and only
+/// letters, digits, `_`, `-` and `.` in the name.
+fn is_read(name: &str) -> bool {
+    let extension_ok = match name.rsplit_once('.') {
+        Some((_, extension)) => extension == "conf",
+        None => true,
+    };
+    extension_ok
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// The files of `dir`, then `main`, the order in which apt reads them.
+fn read_config_in(main: &Path, dir: &Path) -> AptConfig {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+    if main.exists() {
+        paths.push(main.to_path_buf());
+    }
+    let mut statements = Vec::new();
+    let mut diagnostics = Vec::new();
+    for path in paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path != main && !is_read(&name) {
+            if !is_silently_ignored(&name) {
+                diagnostics.push(Diagnostic {
+                    path: path.clone(),
+                    line: None,
+                    severity: Severity::Warning,
+                    code: "wrong-extension",
+                    message: "apt ignores this file, only names without an extension or \
+                              ending in .conf are read"
+                        .to_string(),
+                });
+            }
+            continue;
+        }
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let (file_statements, file_diagnostics) = parse_config(&path, &content);
+                statements.extend(file_statements);
+                diagnostics.extend(file_diagnostics);
+            }
+            Err(e) => diagnostics.push(Diagnostic {
+                path: path.clone(),
+                line: None,
+                severity: Severity::Error,
+                code: "unreadable",
+                message: format!("can't read the file: {e}"),
+            }),
+        }
+    }
+    AptConfig::merge(statements, diagnostics)
+}
+
+/// The configuration of `/etc/apt/apt.conf.d` and `/etc/apt/apt.conf`.
+pub fn read_apt_config() -> AptConfig {
+    read_config_in(Path::new(APT_CONF_PATH), Path::new(APT_CONF_D_PATH))
+}
+
+/// Replaces the value `definition` in its file with `value`, quoted. Fails when the file no
+/// longer has the value where it was read.
+pub fn set_value(definition: &ConfigValue, value: &str) -> Result<()> {
+    if value.contains(['"', '\n']) {
+        return Err(eyre!("Values can't contain quotes or line breaks"));
+    }
+    let span = definition
+        .span
+        .clone()
+        .ok_or_else(|| eyre!("{} has no value to change", definition.key))?;
+    let path = &definition.path;
+    let content =
+        fs::read_to_string(path).map_err(|e| eyre!("Failed to read {}: {e}", path.display()))?;
+    let current = content.get(span.clone()).map(|text| text.trim_matches('"'));
+    if current != Some(definition.value.as_str()) {
+        return Err(eyre!(
+            "{} changed since it was read, refresh and try again",
+            path.display()
+        ));
+    }
+    let mut updated = content[..span.start].to_string();
+    updated.push_str(&format!("\"{value}\""));
+    updated.push_str(&content[span.end..]);
+    write_atomic(path, updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::TempDir;
+
+    const CONFIG: &str = r#"// Proxy for the office
+Acquire::http::Proxy "http://proxy.example:3128";
+APT
+{
+  Periodic {
+    Update-Package-Lists "1";
+    /* twice a week
+       would do too */
+    Unattended-Upgrade "1";
+  };
+  NeverAutoRemove { "^linux-image-.*"; };
+};
+# a comment
+Unattended-Upgrade::Origins-Pattern {
+      "origin=Debian,codename=${distro_codename},label=Debian-Security";
+};
+APT::NeverAutoRemove:: "^firmware-.*";
+Dir::Cache::pkgcache "";
+"#;
+
+    fn summary(config: &AptConfig) -> Vec<(String, Vec<(usize, String)>)> {
+        config
+            .settings
+            .iter()
+            .map(|setting| {
+                let definitions = setting
+                    .definitions
+                    .iter()
+                    .map(|definition| (definition.line, definition.value.clone()))
+                    .collect();
+                (setting.key.clone(), definitions)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let (statements, diagnostics) = parse_config(Path::new("50local"), CONFIG);
+        assert_eq!(diagnostics, Vec::new());
+        let config = AptConfig::merge(statements, diagnostics);
+        assert_eq!(
+            summary(&config),
+            vec![
+                (
+                    "Acquire::http::Proxy".to_string(),
+                    vec![(2, "http://proxy.example:3128".to_string())]
+                ),
+                (
+                    "APT::NeverAutoRemove".to_string(),
+                    vec![
+                        (11, "^linux-image-.*".to_string()),
+                        (17, "^firmware-.*".to_string())
+                    ]
+                ),
+                (
+                    "APT::Periodic::Unattended-Upgrade".to_string(),
+                    vec![(9, "1".to_string())]
+                ),
+                (
+                    "APT::Periodic::Update-Package-Lists".to_string(),
+                    vec![(6, "1".to_string())]
+                ),
+                (
+                    "Dir::Cache::pkgcache".to_string(),
+                    vec![(18, String::new())]
+                ),
+                (
+                    "Unattended-Upgrade::Origins-Pattern".to_string(),
+                    vec![(
+                        15,
+                        "origin=Debian,codename=${distro_codename},label=Debian-Security"
+                            .to_string()
+                    )]
+                ),
+            ]
+        );
+        assert!(config.get("apt::neverautoremove").unwrap().is_list());
+    }
+
+    #[test]
+    fn test_merge_conflicts_and_clear() {
+        let (mut statements, _) = parse_config(Path::new("10first"), CONFIG);
+        let (later, _) = parse_config(
+            Path::new("99later"),
+            "acquire::http::proxy \"false\";\n#clear APT::NeverAutoRemove;\n",
+        );
+        statements.extend(later);
+        let config = AptConfig::merge(statements, Vec::new());
+        let proxy = config.get("Acquire::http::Proxy").unwrap();
+        assert!(proxy.is_conflict());
+        assert_eq!(proxy.effective().location(), "99later:1");
+        assert_eq!(config.get("APT::NeverAutoRemove"), None);
+        assert_eq!(config.conflicts(), 1);
+    }
+
+    #[test]
+    fn test_parse_config_errors() {
+        let lines = |content| {
+            parse_config(Path::new("broken"), content)
+                .1
+                .iter()
+                .map(|diagnostic| (diagnostic.line, diagnostic.message.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lines("APT::Get::Assume-Yes \"true\"\n"),
+            vec![(Some(1), "missing ;".to_string())]
+        );
+        assert_eq!(
+            lines("APT {\n  Get::Assume-Yes \"true\";\n"),
+            vec![(None, "APT isn't closed with }".to_string())]
+        );
+        assert_eq!(
+            lines("Acquire::http::Proxy \"http://proxy;\n"),
+            vec![(Some(1), "unterminated quote".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_set_value() {
+        let dir = TempDir::new("apt-conf");
+        let path = dir.join("95proxy");
+        fs::write(
+            &path,
+            "Acquire::http::Proxy \"http://old:3128\"; // office\n",
+        )
+        .unwrap();
+        let (statements, _) = parse_config(&path, &fs::read_to_string(&path).unwrap());
+        let ConfigStatement::Set(definition) = &statements[0] else {
+            panic!("expected a value");
+        };
+        set_value(definition, "http://new:8080").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "Acquire::http::Proxy \"http://new:8080\"; // office\n"
+        );
+        // The file no longer has the value that was read
+        assert!(set_value(definition, "DIRECT").is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use color_eyre::{eyre::eyre, Result};
+use std::fs;
+
+use super::{
+    auth::strip_credentials,
+    dpkg::InstalledPackage,
+    edit::{self, write_atomic},
+    lists::release_fields,
+    AptRepositories, AptSource, SourceFormat, SourceRef,
+};
+use crate::repositories::Repository;
+
+/// Every architecture dpkg is set up for, written by `dpkg --add-architecture`. Systems that
+/// never added one don't have the file.
+pub const DPKG_ARCH_PATH: &str = "/var/lib/dpkg/arch";
+
+/// Components of the Debian and Ubuntu archives, offered for their sources while apt hasn't
+/// fetched a Release file that lists them.
+const DEBIAN_COMPONENTS: [&str; 4] = ["main", "contrib", "non-free", "non-free-firmware"];
+const UBUNTU_COMPONENTS: [&str; 4] = ["main", "restricted", "universe", "multiverse"];
+
+/// The architectures dpkg installs packages for: its own, which is the architecture of the
+/// `dpkg` package, then the foreign ones. Like `dpkg --print-architecture` followed by
+/// `dpkg --print-foreign-architectures`.
+pub fn dpkg_architectures(installed: &[InstalledPackage]) -> Vec<String> {
+    let native = installed
+        .iter()
+        .find(|package| package.name == "dpkg")
+        .map(|package| package.architecture.as_str());
+    parse_architectures(
+        &fs::read_to_string(DPKG_ARCH_PATH).unwrap_or_default(),
+        native,
+    )
+}
+
+fn parse_architectures(content: &str, native: Option<&str>) -> Vec<String> {
+    let mut architectures: Vec<String> = native.into_iter().map(str::to_string).collect();
+    for architecture in content.split_whitespace() {
+        if architecture != "all" && !architectures.iter().any(|known| known == architecture) {
+            architectures.push(architecture.to_string());
+        }
+    }
+    architectures
+}
+
+/// A checkbox of the component editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choice {
+    pub name: String,
+    pub checked: bool,
+}
+
+/// The components and architectures of one source, as the user is toggling them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentEdit {
+    pub source_ref: SourceRef,
+    /// Empty for an exact path suite, which has no components.
+    pub components: Vec<Choice>,
+    pub architectures: Vec<Choice>,
+    /// The architectures dpkg is set up for. When all of them are checked the source needs no
+    /// restriction.
+    dpkg_architectures: Vec<String>,
+    /// The architectures the source is restricted to now, if it is.
+    restricted: Option<Vec<String>>,
+    /// The other sources of the same deb822 stanza, which get the same change.
+    pub shared_with: Vec<SourceRef>,
+}
+
+impl ComponentEdit {
+    /// How many checkboxes there are, components first.
+    pub fn len(&self) -> usize {
+        self.components.len() + self.architectures.len()
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        let choice = match index.checked_sub(self.components.len()) {
+            None => self.components.get_mut(index),
+            Some(index) => self.architectures.get_mut(index),
+        };
+        if let Some(choice) = choice {
+            choice.checked = !choice.checked;
+        }
+    }
+
+    fn checked(choices: &[Choice]) -> Vec<String> {
+        choices
+            .iter()
+            .filter(|choice| choice.checked)
+            .map(|choice| choice.name.clone())
+            .collect()
+    }
+
+    pub fn checked_components(&self) -> Vec<String> {
+        Self::checked(&self.components)
+    }
+
+    /// The `arch` option to write, empty when the source should get every architecture dpkg
+    /// is set up for. A restriction left as it was is kept, even if it names them all.
+    pub fn architectures_option(&self) -> Vec<String> {
+        let checked = Self::checked(&self.architectures);
+        if self.restricted.as_ref() == Some(&checked) {
+            return checked;
+        }
+        let everything = checked.len() == self.dpkg_architectures.len()
+            && self
+                .dpkg_architectures
+                .iter()
+                .all(|architecture| checked.contains(architecture));
+        if everything {
+            Vec::new()
+        } else {
+            checked
+        }
+    }
+
+    /// Why the edit can't be written, if it can't. Without any architecture to choose from,
+    /// when dpkg's aren't known and the source has no restriction, there is nothing to check.
+    pub fn problem(&self) -> Option<&'static str> {
+        if !self.components.is_empty() && self.checked_components().is_empty() {
+            Some("check at least one component")
+        } else if !self.architectures.is_empty()
+            && self.architectures.iter().all(|choice| !choice.checked)
+        {
+            Some("check at least one architecture")
+        } else {
+            None
+        }
+    }
+}
+
+/// The components a Release file lists. Old security archives list them as `updates/main`.
+fn release_components(release: &str) -> Vec<String> {
+    release_fields(release)
+        .into_iter()
+        .find(|(key, _)| *key == "Components")
+        .map(|(_, value)| {
+            value
+                .split_whitespace()
+                .map(|component| component.rsplit('/').next().unwrap_or(component))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The components of the Debian or Ubuntu archive `source` points at, recognized by one of
+/// their components besides main or by the host of the official mirrors.
+fn archive_components(source: &AptSource) -> Option<[&'static str; 4]> {
+    let host = strip_credentials(&source.uri)
+        .split_once("://")
+        .map(|(_, rest)| {
+            rest.split('/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase()
+        })
+        .unwrap_or_default();
+    [
+        (DEBIAN_COMPONENTS, host.ends_with("debian.org")),
+        (UBUNTU_COMPONENTS, host.ends_with("ubuntu.com")),
+    ]
+    .into_iter()
+    .find(|(known, official)| {
+        *official
+            || source
+                .components
+                .iter()
+                .any(|component| component != "main" && known.contains(&component.as_str()))
+    })
+    .map(|(known, _)| known)
+}
+
+impl AptRepositories {
+    /// The editor for `source_ref`: its components next to the ones `release`, the cached
+    /// Release file, offers, and the architectures dpkg is set up for.
+    pub fn plan_component_edit(
+        &self,
+        source_ref: SourceRef,
+        release: Option<&str>,
+        dpkg_architectures: &[String],
+    ) -> Result<ComponentEdit> {
+        let (apt_list, source) = self
+            .get(source_ref)
+            .ok_or_else(|| eyre!("No source at {source_ref:?}"))?;
+
+        let mut components = Vec::new();
+        if !source.suite.ends_with('/') {
+            let offered = release
+                .map(release_components)
+                .filter(|offered| !offered.is_empty())
+                .or_else(|| {
+                    archive_components(source).map(|known| known.map(str::to_string).to_vec())
+                })
+                .unwrap_or_default();
+            for name in source.components.iter().chain(&offered) {
+                if !components
+                    .iter()
+                    .any(|choice: &Choice| &choice.name == name)
+                {
+                    components.push(Choice {
+                        name: name.clone(),
+                        checked: source.components.contains(name),
+                    });
+                }
+            }
+        }
+
+        let restricted: Option<Vec<String>> = source.option("arch").map(|value| {
+            value
+                .split(',')
+                .filter(|arch| !arch.is_empty())
+                .map(str::to_string)
+                .collect()
+        });
+        let mut architectures: Vec<Choice> = dpkg_architectures
+            .iter()
+            .map(|name| Choice {
+                name: name.clone(),
+                checked: restricted
+                    .as_ref()
+                    .is_none_or(|restricted| restricted.contains(name)),
+            })
+            .collect();
+        // Architectures dpkg doesn't know stay listed so they can be unchecked
+        for name in restricted.iter().flatten() {
+            if !architectures.iter().any(|choice| choice.name == *name) {
+                architectures.push(Choice {
+                    name: name.clone(),
+                    checked: true,
+                });
+            }
+        }
+
+        let shared_with = match apt_list.format {
+            SourceFormat::OneLine => Vec::new(),
+            SourceFormat::Deb822 => self
+                .source_refs()
+                .filter(|(other_ref, _, other)| {
+                    other_ref.list == source_ref.list
+                        && *other_ref != source_ref
+                        && other.line_number == source.line_number
+                })
+                .map(|(other_ref, _, _)| other_ref)
+                .collect(),
+        };
+
+        Ok(ComponentEdit {
+            source_ref,
+            components,
+            architectures,
+            dpkg_architectures: dpkg_architectures.to_vec(),
+            restricted,
+            shared_with,
+        })
+    }
+
+    /// The content of the source's file before and after the edit.
+    pub fn preview_component_edit(&self, plan: &ComponentEdit) -> Result<(String, String)> {
+        let (apt_list, source) = self
+            .get(plan.source_ref)
+            .ok_or_else(|| eyre!("No source at {:?}", plan.source_ref))?;
+        let content = fs::read_to_string(&apt_list.file_path)
+            .map_err(|e| eyre!("Failed to read {}: {e}", apt_list.file_path.display()))?;
+        let updated = edit::set_components_in_content(
+            &content,
+            apt_list.format,
+            source,
+            &plan.checked_components(),
+            &plan.architectures_option(),
+        )?;
+        Ok((content, updated))
+    }
+
+    /// Writes the edit back to the source's file and reloads the lists. Returns whether the
+    /// file changed.
+    pub fn apply_component_edit(&mut self, plan: &ComponentEdit) -> Result<bool> {
+        if let Some(problem) = plan.problem() {
+            return Err(eyre!("Can't write the source: {problem}"));
+        }
+        let (content, updated) = self.preview_component_edit(plan)?;
+        if updated == content {
+            return Ok(false);
+        }
+        let (apt_list, _) = self
+            .get(plan.source_ref)
+            .ok_or_else(|| eyre!("No source at {:?}", plan.source_ref))?;
+        write_atomic(&apt_list.file_path, updated)?;
+        self.load_repository_list()?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::repositories;
+
+    #[test]
+    fn test_plan_component_edit() {
+        assert_eq!(
+            parse_architectures("amd64\ni386\nall\n", Some("amd64")),
+            vec!["amd64", "i386"]
+        );
+        let content = "Types: deb deb-src\nURIs: http://deb.debian.org/debian\nSuites: bookworm\nComponents: main non-free-firmware\n\nTypes: deb\nURIs: https://example.com/apt\nSuites: stable\nComponents: main\nArchitectures: amd64\n";
+        let repositories = repositories()
+            .list("/etc/apt/sources.list.d/test.sources", content)
+            .build();
+        let architectures = vec!["amd64".to_string(), "i386".to_string()];
+        let names = |choices: &[Choice]| -> Vec<(String, bool)> {
+            choices
+                .iter()
+                .map(|choice| (choice.name.clone(), choice.checked))
+                .collect()
+        };
+        let owned = |pairs: &[(&str, bool)]| -> Vec<(String, bool)> {
+            pairs
+                .iter()
+                .map(|(name, checked)| (name.to_string(), *checked))
+                .collect()
+        };
+
+        let debian = SourceRef { list: 0, source: 0 };
+        let mut plan = repositories
+            .plan_component_edit(debian, None, &architectures)
+            .unwrap();
+        assert_eq!(
+            names(&plan.components),
+            owned(&[
+                ("main", true),
+                ("non-free-firmware", true),
+                ("contrib", false),
+                ("non-free", false),
+            ])
+        );
+        assert_eq!(plan.shared_with, vec![SourceRef { list: 0, source: 1 }]);
+        assert_eq!(plan.architectures_option(), Vec::<String>::new());
+        plan.toggle(2);
+        plan.toggle(5);
+        assert_eq!(
+            plan.checked_components(),
+            vec!["main", "non-free-firmware", "contrib"]
+        );
+        assert_eq!(plan.architectures_option(), vec!["amd64"]);
+
+        let vendor = SourceRef { list: 0, source: 2 };
+        let mut plan = repositories
+            .plan_component_edit(
+                vendor,
+                Some("Origin: Example\nComponents: main beta\n"),
+                &architectures,
+            )
+            .unwrap();
+        assert_eq!(
+            names(&plan.components),
+            owned(&[("main", true), ("beta", false)])
+        );
+        assert_eq!(
+            names(&plan.architectures),
+            owned(&[("amd64", true), ("i386", false)])
+        );
+        plan.toggle(0);
+        assert_eq!(plan.problem(), Some("check at least one component"));
+
+        let native = vec!["amd64".to_string()];
+        let plan = repositories
+            .plan_component_edit(vendor, None, &native)
+            .unwrap();
+        assert_eq!(plan.architectures_option(), vec!["amd64"]);
+
+        // dpkg's architectures unknown, the components can still be edited
+        let mut plan = repositories.plan_component_edit(debian, None, &[]).unwrap();
+        assert_eq!(plan.architectures, Vec::new());
+        plan.toggle(2);
+        assert_eq!(plan.problem(), None);
+        assert_eq!(plan.architectures_option(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,332 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{
+    edit::write_atomic, one_line, AptList, AptRepositories, AptSource, SourceFormat, SourceType,
+    APT_SOURCES_LIST_D_PATH, DEB822_OPTION_NAMES,
+};
+use crate::repositories::Repository;
+
+/// Where the entries of the primary `sources.list` go, the name `apt modernize-sources` uses.
+const PRIMARY_TARGET_NAME: &str = "moved-from-main.sources";
+
+/// One-line options whose deb822 name isn't the option name in title case.
+const DEB822_SPECIAL_NAMES: [(&str, &str); 2] =
+    [("pdiffs", "PDiffs"), ("inrelease-path", "InRelease-Path")];
+
+/// The deb822 field for a one-line option, e.g. `Signed-By` for `signed-by` and
+/// `Architectures-Add` for `arch+`.
+pub(super) fn deb822_field_name(key: &str) -> String {
+    let (name, suffix) = if let Some(name) = key.strip_suffix('+') {
+        (name, "-Add")
+    } else if let Some(name) = key.strip_suffix('-') {
+        (name, "-Remove")
+    } else {
+        (key, "")
+    };
+    let field = DEB822_OPTION_NAMES
+        .iter()
+        .map(|(field, option)| (*option, *field))
+        .chain(DEB822_SPECIAL_NAMES)
+        .find(|(option, _)| option.eq_ignore_ascii_case(name))
+        .map(|(_, field)| field.to_string())
+        .unwrap_or_else(|| {
+            name.split('-')
+                .map(|part| {
+                    let mut chars = part.chars();
+                    chars
+                        .next()
+                        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>()
+                .join("-")
+        });
+    format!("{field}{suffix}")
+}
+
+/// Entries that only differ in type or suite, merged the way they would be written by hand.
+#[derive(Debug, Default)]
+struct Stanza<'a> {
+    comments: Vec<&'a str>,
+    enabled: bool,
+    options: &'a [(String, String)],
+    uri: &'a str,
+    components: &'a [String],
+    types: Vec<SourceType>,
+    suites: Vec<&'a str>,
+}
+
+impl Stanza<'_> {
+    fn render(&self) -> String {
+        let mut text = String::new();
+        for comment in &self.comments {
+            text.push_str(comment);
+            text.push('\n');
+        }
+        let types: Vec<String> = self.types.iter().map(SourceType::to_string).collect();
+        text.push_str(&format!("Types: {}\n", types.join(" ")));
+        text.push_str(&format!("URIs: {}\n", self.uri));
+        text.push_str(&format!("Suites: {}\n", self.suites.join(" ")));
+        if !self.components.is_empty() {
+            text.push_str(&format!("Components: {}\n", self.components.join(" ")));
+        }
+        for (key, value) in self.options {
+            text.push_str(&format!(
+                "{}: {}\n",
+                deb822_field_name(key),
+                value.replace(',', " ")
+            ));
+        }
+        if !self.enabled {
+            text.push_str("Enabled: no\n");
+        }
+        text
+    }
+}
+
+/// Everything about an entry but its type: enabled, options, URI, components and suite.
+type EntryKey<'a> = (bool, &'a [(String, String)], &'a str, &'a [String], &'a str);
+
+/// Renders the entries of a one-line file as deb822 stanzas. Comment lines right above an
+/// entry are kept above its stanza; comments at the end of an entry are lost.
+pub fn to_deb822(content: &str, sources: &[AptSource]) -> Result<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let comments_above = |source: &AptSource| -> Vec<&str> {
+        let above = &lines[..source.line_number.saturating_sub(1).min(lines.len())];
+        let start = above
+            .iter()
+            .rposition(|line| {
+                !line.trim_start().starts_with('#')
+                    || one_line::parse_line(line, 0).is_ok_and(|source| source.is_some())
+            })
+            .map_or(0, |position| position + 1);
+        above[start..].to_vec()
+    };
+
+    // Types first, then suites, so that every type and suite combination of a stanza was in
+    // the original file.
+    let mut by_suite: BTreeMap<EntryKey, Vec<&AptSource>> = BTreeMap::new();
+    let mut order = Vec::new();
+    for source in sources {
+        if source.uri.contains(char::is_whitespace) {
+            return Err(eyre!(
+                "line {}: `{}` can't be written as a deb822 URI",
+                source.line_number,
+                source.uri
+            ));
+        }
+        let key = (
+            source.enabled,
+            source.options.as_slice(),
+            source.uri.as_str(),
+            source.components.as_slice(),
+            source.suite.as_str(),
+        );
+        if !by_suite.contains_key(&key) {
+            order.push(key);
+        }
+        by_suite.entry(key).or_default().push(source);
+    }
+
+    let mut stanzas: Vec<Stanza> = Vec::new();
+    for key in order {
+        let entries = &by_suite[&key];
+        let (enabled, options, uri, components, suite) = key;
+        let mut types: Vec<SourceType> = entries.iter().map(|source| source.source_type).collect();
+        types.sort();
+        types.dedup();
+        let comments: Vec<&str> = entries
+            .iter()
+            .flat_map(|source| comments_above(source))
+            .collect();
+
+        let existing = stanzas.iter_mut().find(|stanza| {
+            stanza.enabled == enabled
+                && stanza.options == options
+                && stanza.uri == uri
+                && stanza.components == components
+                && stanza.types == types
+        });
+        match existing {
+            Some(stanza) => {
+                if !stanza.suites.contains(&suite) {
+                    stanza.suites.push(suite);
+                }
+                stanza.comments.extend(comments);
+            }
+            None => stanzas.push(Stanza {
+                comments,
+                enabled,
+                options,
+                uri,
+                components,
+                types,
+                suites: vec![suite],
+            }),
+        }
+    }
+
+    Ok(stanzas
+        .iter()
+        .map(Stanza::render)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// A `.list` file about to be replaced by a `.sources` file.
+#[derive(Debug, Default, Clone)]
+pub struct Conversion {
+    pub list_path: PathBuf,
+    pub target_path: PathBuf,
+    /// The original is renamed to this, which apt ignores.
+    pub backup_path: PathBuf,
+    pub content: String,
+}
+
+/// `path` with `.bak` appended, a suffix apt ignores.
+pub(super) fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+impl AptRepositories {
+    /// Computes the deb822 version of the one-line list at `list_index` without writing it.
+    pub fn plan_conversion(&self, list_index: usize) -> Result<Conversion> {
+        let apt_list = self
+            .items
+            .get(list_index)
+            .ok_or_else(|| eyre!("No source file at index {list_index}"))?;
+        Self::conversion_of(apt_list)
+    }
+
+    /// Plans every one-line list that has entries, along with the lists that can't be
+    /// converted and why.
+    pub fn plan_all_conversions(&self) -> (Vec<Conversion>, Vec<(PathBuf, String)>) {
+        let mut conversions = Vec::new();
+        let mut failures = Vec::new();
+        for apt_list in &self.items {
+            if apt_list.format != SourceFormat::OneLine || apt_list.sources.is_empty() {
+                continue;
+            }
+            match Self::conversion_of(apt_list) {
+                Ok(conversion) => conversions.push(conversion),
+                Err(e) => failures.push((apt_list.file_path.clone(), e.to_string())),
+            }
+        }
+        (conversions, failures)
+    }
+
+    fn conversion_of(apt_list: &AptList) -> Result<Conversion> {
+        if apt_list.format != SourceFormat::OneLine {
+            return Err(eyre!(
+                "{} is already in deb822 format",
+                apt_list.file_path.display()
+            ));
+        }
+        if apt_list.sources.is_empty() {
+            return Err(eyre!("{} has no entries", apt_list.file_path.display()));
+        }
+        let target_path = if apt_list.primary {
+            Path::new(APT_SOURCES_LIST_D_PATH).join(PRIMARY_TARGET_NAME)
+        } else {
+            apt_list.file_path.with_extension("sources")
+        };
+        let backup_path = backup_path(&apt_list.file_path);
+        for path in [&target_path, &backup_path] {
+            if path.exists() {
+                return Err(eyre!("{} already exists", path.display()));
+            }
+        }
+        let content = fs::read_to_string(&apt_list.file_path)?;
+        Ok(Conversion {
+            list_path: apt_list.file_path.clone(),
+            target_path,
+            backup_path,
+            content: to_deb822(&content, &apt_list.sources)?,
+        })
+    }
+
+    /// Writes the new files, moves the originals to their backups and reloads the lists.
+    pub fn apply_conversions(&mut self, conversions: &[Conversion]) -> Result<usize> {
+        for conversion in conversions {
+            for path in [&conversion.target_path, &conversion.backup_path] {
+                if path.exists() {
+                    return Err(eyre!("{} already exists", path.display()));
+                }
+            }
+            write_atomic(&conversion.target_path, &conversion.content)?;
+            if let Err(e) = fs::rename(&conversion.list_path, &conversion.backup_path) {
+                // Leaving both would make apt read every entry twice
+                let _ = fs::remove_file(&conversion.target_path);
+                return Err(eyre!(
+                    "Failed to move {} to {}: {e}",
+                    conversion.list_path.display(),
+                    conversion.backup_path.display()
+                ));
+            }
+        }
+        self.load_repository_list()?;
+        Ok(conversions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_deb822_field_name() {
+        assert_eq!(deb822_field_name("signed-by"), "Signed-By");
+        assert_eq!(deb822_field_name("arch"), "Architectures");
+        assert_eq!(deb822_field_name("arch-"), "Architectures-Remove");
+        assert_eq!(deb822_field_name("pdiffs"), "PDiffs");
+    }
+
+    #[test]
+    fn test_to_deb822() {
+        let content = "\
+# Debian archive
+deb [arch=amd64,arm64 signed-by=/usr/share/keyrings/debian-archive-keyring.gpg] http://deb.debian.org/debian bookworm main
+deb [arch=amd64,arm64 signed-by=/usr/share/keyrings/debian-archive-keyring.gpg] http://deb.debian.org/debian bookworm-updates main
+deb-src [arch=amd64,arm64 signed-by=/usr/share/keyrings/debian-archive-keyring.gpg] http://deb.debian.org/debian bookworm main
+
+# deb http://deb.debian.org/debian bookworm-backports main
+";
+        let sources: Vec<AptSource> = content
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| one_line::parse_line(line, index + 1).unwrap())
+            .collect();
+        assert_eq!(
+            to_deb822(content, &sources).unwrap(),
+            "\
+# Debian archive
+Types: deb deb-src
+URIs: http://deb.debian.org/debian
+Suites: bookworm
+Components: main
+Architectures: amd64 arm64
+Signed-By: /usr/share/keyrings/debian-archive-keyring.gpg
+
+Types: deb
+URIs: http://deb.debian.org/debian
+Suites: bookworm-updates
+Components: main
+Architectures: amd64 arm64
+Signed-By: /usr/share/keyrings/debian-archive-keyring.gpg
+
+Types: deb
+URIs: http://deb.debian.org/debian
+Suites: bookworm-backports
+Components: main
+Enabled: no
+"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use color_eyre::{eyre::eyre, Result};
+
+/// One paragraph of a deb822 file such as `/etc/apt/sources.list.d/debian.sources`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Deb822Stanza {
+    /// 1-based line the stanza starts on.
+    pub line_number: usize,
+    /// Number of lines the stanza spans, including comments inside it.
+    pub line_count: usize,
+    /// Fields in file order. Multi-line values keep their line breaks, with the folding space
+    /// and the ` .` empty line markers removed.
+    pub fields: Vec<(String, String)>,
+}
+
+impl Deb822Stanza {
+    /// Field names are case-insensitive in deb822.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whitespace separated values of a field such as `Suites` or `Components`.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        self.get(key)
+            .map(|v| v.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Splits deb822 text into stanzas.
+///
+/// Comment lines are skipped wherever they appear, so a stanza with every line commented out
+/// is not returned at all.
+pub fn parse_stanzas(content: &str) -> Result<Vec<Deb822Stanza>> {
+    let mut stanzas = Vec::new();
+    let mut current: Option<Deb822Stanza> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+
+        if line.trim().is_empty() {
+            if let Some(stanza) = current.take() {
+                stanzas.push(stanza);
+            }
+            continue;
+        }
+
+        if line.trim_start().starts_with('#') {
+            if let Some(stanza) = current.as_mut() {
+                stanza.line_count = line_number - stanza.line_number + 1;
+            }
+            continue;
+        }
+
+        if line.starts_with(' ') || line.starts_with('\t') {
+            let Some((_, value)) = current.as_mut().and_then(|stanza| stanza.fields.last_mut())
+            else {
+                return Err(eyre!(
+                    "line {line_number}: continuation line without a field"
+                ));
+            };
+            let continuation = line.trim();
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            if continuation != "." {
+                value.push_str(continuation);
+            }
+        } else {
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(eyre!("line {line_number}: expected `Field: value`"));
+            };
+            let key = key.trim();
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return Err(eyre!("line {line_number}: invalid field name `{key}`"));
+            }
+            let stanza = current.get_or_insert_with(|| Deb822Stanza {
+                line_number,
+                ..Deb822Stanza::default()
+            });
+            stanza
+                .fields
+                .push((key.to_string(), value.trim().to_string()));
+        }
+
+        if let Some(stanza) = current.as_mut() {
+            stanza.line_count = line_number - stanza.line_number + 1;
+        }
+    }
+
+    if let Some(stanza) = current.take() {
+        stanzas.push(stanza);
+    }
+    Ok(stanzas)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_stanzas_with_comments() {
+        let content = "\
+Types: deb
+# http://snapshot.debian.org/archive/debian/20250520T000000Z
+URIs: http://deb.debian.org/debian
+Suites: bookworm bookworm-updates
+Components: main
+Signed-By: /usr/share/keyrings/debian-archive-keyring.gpg
+
+Types: deb
+URIs: http://deb.debian.org/debian-security
+Suites: bookworm-security
+Components: main
+";
+        let stanzas = parse_stanzas(content).unwrap();
+        assert_eq!(stanzas.len(), 2);
+        assert_eq!(stanzas[0].line_number, 1);
+        assert_eq!(stanzas[0].line_count, 6);
+        assert_eq!(
+            stanzas[0].get_list("suites"),
+            vec!["bookworm", "bookworm-updates"]
+        );
+        assert_eq!(stanzas[1].line_number, 8);
+        assert_eq!(
+            stanzas[1].get("URIs"),
+            Some("http://deb.debian.org/debian-security")
+        );
+    }
+
+    #[test]
+    fn test_parse_stanzas_inline_key() {
+        let content = "\
+Types: deb
+URIs: https://example.com/apt
+Suites: stable
+Components: main
+Signed-By:
+ -----BEGIN PGP PUBLIC KEY BLOCK-----
+ .
+ mDMEZ
+ -----END PGP PUBLIC KEY BLOCK-----
+Enabled: no
+";
+        let stanzas = parse_stanzas(content).unwrap();
+        assert_eq!(stanzas.len(), 1);
+        assert_eq!(stanzas[0].line_count, 10);
+        assert_eq!(
+            stanzas[0].get("Signed-By"),
+            Some(
+                "-----BEGIN PGP PUBLIC KEY BLOCK-----\n\nmDMEZ\n-----END PGP PUBLIC KEY BLOCK-----"
+            )
+        );
+        assert_eq!(stanzas[0].get("enabled"), Some("no"));
+    }
+
+    #[test]
+    fn test_parse_stanzas_malformed() {
+        assert!(parse_stanzas("Types: deb\nnot a field\n").is_err());
+        assert!(parse_stanzas(" leading continuation\n").is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::time::SystemTime;
+
+use super::{
+    lists::{IndexCache, SourceLists},
+    AptRepositories, SourceRef, SourceType,
+};
+
+/// The database of installed packages dpkg keeps.
+pub const DPKG_STATUS_PATH: &str = "/var/lib/dpkg/status";
+
+/// A package version dpkg has installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub architecture: String,
+}
+
+/// The installed packages of a dpkg status file. Packages that are only configured, half
+/// removed or just selected for installation are left out.
+pub fn parse_status(content: &str) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+    for stanza in content.split("\n\n") {
+        let field = |name: &str| {
+            stanza.lines().find_map(|line| {
+                line.strip_prefix(name)
+                    .and_then(|rest| rest.strip_prefix(':'))
+                    .map(|value| value.trim().to_string())
+            })
+        };
+        let installed = field("Status").is_some_and(|status| status.ends_with(" installed"));
+        if let (true, Some(name), Some(version)) = (installed, field("Package"), field("Version")) {
+            packages.push(InstalledPackage {
+                name,
+                version,
+                architecture: field("Architecture").unwrap_or_default(),
+            });
+        }
+    }
+    packages
+}
+
+/// The packages installed on this system.
+pub fn installed_packages() -> Result<Vec<InstalledPackage>> {
+    let content = fs::read_to_string(DPKG_STATUS_PATH)
+        .map_err(|e| eyre!("Failed to read {DPKG_STATUS_PATH}: {e}"))?;
+    Ok(parse_status(&content))
+}
+
+/// The installed packages, read again only when dpkg changed its status file.
+#[derive(Debug, Default)]
+pub struct StatusCache {
+    modified: Option<SystemTime>,
+    packages: Vec<InstalledPackage>,
+}
+
+impl StatusCache {
+    /// Reads the status file if it changed since the last call. Returns whether it did.
+    pub fn refresh(&mut self) -> bool {
+        let modified = fs::metadata(DPKG_STATUS_PATH)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_some() && modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        self.packages = installed_packages().unwrap_or_default();
+        true
+    }
+
+    pub fn packages(&self) -> &[InstalledPackage] {
+        &self.packages
+    }
+}
+
+/// The architecture of a `binary-<arch>` index, from its file name.
+fn index_architecture(name: &str) -> Option<&str> {
+    let (_, rest) = name.split_once("_binary-")?;
+    rest.split_once('_').map(|(architecture, _)| architecture)
+}
+
+impl SourceLists {
+    /// Names of the installed packages whose installed version is in one of the `Packages`
+    /// indexes, or `None` when none of them could be read. Several sources can provide the
+    /// same version, apt may have installed it from any of them.
+    pub fn installed_packages(
+        &self,
+        cache: &mut IndexCache,
+        installed: &[InstalledPackage],
+    ) -> Option<Vec<String>> {
+        let mut by_name: HashMap<&str, Vec<&InstalledPackage>> = HashMap::new();
+        for package in installed {
+            by_name.entry(&package.name).or_default().push(package);
+        }
+        let mut provided = BTreeSet::new();
+        let mut read_any = false;
+        for index in &self.indexes {
+            let name = index.file_name().unwrap_or_default().to_string_lossy();
+            if !name.contains("_Packages") {
+                continue;
+            }
+            let architecture = index_architecture(&name);
+            let Ok(packages) = cache.packages(index) else {
+                continue;
+            };
+            read_any = true;
+            for package in packages {
+                let matches = by_name.get(package.name.as_str()).is_some_and(|versions| {
+                    versions.iter().any(|installed| {
+                        installed.version == package.version
+                            && (installed.architecture == "all"
+                                || architecture.is_none_or(|architecture| {
+                                    architecture == "all" || architecture == installed.architecture
+                                }))
+                    })
+                });
+                if matches {
+                    provided.insert(package.name.clone());
+                }
+            }
+        }
+        read_any.then(|| provided.into_iter().collect())
+    }
+}
+
+impl AptRepositories {
+    /// The installed packages each enabled `deb` source provides. Sources without a readable
+    /// index are left out, as nothing is known about them.
+    pub fn installed_by_source(
+        &self,
+        lists: &HashMap<SourceRef, SourceLists>,
+        cache: &mut IndexCache,
+        installed: &[InstalledPackage],
+    ) -> HashMap<SourceRef, Vec<String>> {
+        self.source_refs()
+            .filter(|(_, _, source)| source.enabled && source.source_type == SourceType::Deb)
+            .filter_map(|(source_ref, _, _)| {
+                let provided = lists
+                    .get(&source_ref)?
+                    .installed_packages(cache, installed)?;
+                Some((source_ref, provided))
+            })
+            .collect()
+    }
+
+    /// Enabled `deb` sources that provide nothing installed, and the `deb-src` sources sharing
+    /// their entry. A deb822 stanza is only included when all of its `deb` sources are unused,
+    /// since it is disabled as a whole.
+    pub fn unused_sources(
+        &self,
+        installed_by_source: &HashMap<SourceRef, Vec<String>>,
+    ) -> Vec<SourceRef> {
+        // Per entry: whether it has a `deb` source, whether they are all unused, its sources
+        let mut entries: BTreeMap<(usize, usize), (bool, bool, Vec<SourceRef>)> = BTreeMap::new();
+        for (source_ref, _, source) in self.source_refs() {
+            if !source.enabled {
+                continue;
+            }
+            let (has_deb, unused, refs) = entries
+                .entry((source_ref.list, source.line_number))
+                .or_insert((false, true, Vec::new()));
+            if source.source_type == SourceType::Deb {
+                *has_deb = true;
+                *unused &= installed_by_source
+                    .get(&source_ref)
+                    .is_some_and(|provided| provided.is_empty());
+            }
+            refs.push(source_ref);
+        }
+        entries
+            .into_values()
+            .filter(|(has_deb, unused, _)| *has_deb && *unused)
+            .flat_map(|(_, _, refs)| refs)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use std::path::Path;
+
+    use super::*;
+    use crate::repositories::apt::{lists::list_file_names, test_support::repositories};
+
+    const STATUS: &str = "\
+Package: legacy-tool
+Status: install ok installed
+Architecture: amd64
+Version: 1.2.0
+Description: a tool
+ spanning two lines
+
+Package: legacy-docs
+Status: deinstall ok config-files
+Architecture: all
+Version: 1.2.0
+
+Package: legacy-daemon
+Status: install ok installed
+Architecture: amd64
+Version: 0.9.0
+";
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(
+            parse_status(STATUS),
+            vec![
+                InstalledPackage {
+                    name: "legacy-tool".to_string(),
+                    version: "1.2.0".to_string(),
+                    architecture: "amd64".to_string(),
+                },
+                InstalledPackage {
+                    name: "legacy-daemon".to_string(),
+                    version: "0.9.0".to_string(),
+                    architecture: "amd64".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_sources() {
+        let lists_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lists");
+        let names = list_file_names(&lists_dir);
+        let content = "\
+Types: deb deb-src
+URIs: http://legacy.example/apt
+Suites: stable
+Components: main
+";
+        let repositories = repositories()
+            .list("/etc/apt/sources.list.d/legacy.sources", content)
+            .list(
+                "/etc/apt/sources.list.d/gone.list",
+                "deb http://gone.example/apt stable main",
+            )
+            .build();
+        let lists: HashMap<SourceRef, SourceLists> = repositories
+            .source_refs()
+            .map(|(source_ref, _, source)| (source_ref, source.lists(&lists_dir, &names)))
+            .collect();
+        let mut cache = IndexCache::default();
+
+        let installed = parse_status(STATUS);
+        let provided = repositories.installed_by_source(&lists, &mut cache, &installed);
+        let legacy = SourceRef { list: 0, source: 0 };
+        assert_eq!(provided.len(), 1);
+        assert_eq!(provided[&legacy], vec!["legacy-tool"]);
+        assert_eq!(repositories.unused_sources(&provided), Vec::new());
+
+        let installed = parse_status(&STATUS.replace("1.2.0", "1.1.0"));
+        let provided = repositories.installed_by_source(&lists, &mut cache, &installed);
+        assert_eq!(provided[&legacy], Vec::<String>::new());
+        // The unfetched source may well be in use, so only the stanza is unused
+        assert_eq!(
+            repositories.unused_sources(&provided),
+            vec![legacy, SourceRef { list: 0, source: 1 }]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use color_eyre::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{AptRepositories, AptSource, SourceRef, SourceType};
+
+/// Enabled sources that make apt fetch the same components of a repository more than once.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub source_type: SourceType,
+    /// Normalized URI shared by the group.
+    pub uri: String,
+    pub suite: String,
+    /// Components configured by more than one of the sources.
+    pub components: Vec<String>,
+    pub sources: Vec<SourceRef>,
+}
+
+/// Reduces a URI to what apt considers the same repository: case-insensitive scheme and host,
+/// no credentials and no trailing slash. `http` and `https` stay apart, so consolidating never
+/// drops the secure one.
+pub fn normalize_uri(uri: &str) -> String {
+    let uri = uri.trim().trim_end_matches('/');
+    let (scheme, rest) = uri.split_once("://").unwrap_or(("", uri));
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let mut normalized = format!(
+        "{}://{}",
+        scheme.to_ascii_lowercase(),
+        host.to_ascii_lowercase()
+    );
+    if !path.is_empty() {
+        normalized.push('/');
+        normalized.push_str(path);
+    }
+    normalized
+}
+
+/// What consolidating the duplicates would change.
+#[derive(Debug, Default, Clone)]
+pub struct ConsolidationPlan {
+    /// The source kept for each group.
+    pub keep: Vec<SourceRef>,
+    /// Sources whose components are all covered by the kept source.
+    pub disable: Vec<SourceRef>,
+    /// Duplicates that need a manual merge, with the reason.
+    pub skipped: Vec<(SourceRef, String)>,
+}
+
+impl AptRepositories {
+    /// Groups the enabled sources that overlap by type, URI, suite and at least one component.
+    /// Sources restricted to different architectures fetch different indexes, so they are
+    /// only grouped when their `arch` options list the same ones.
+    pub fn find_duplicates(&self) -> Vec<DuplicateGroup> {
+        type Key = (SourceType, String, String, Vec<String>);
+        let mut candidates: BTreeMap<Key, Vec<SourceRef>> = BTreeMap::new();
+        for (source_ref, _, source) in self.source_refs() {
+            if source.enabled {
+                let mut architectures: Vec<String> = source
+                    .option("arch")
+                    .unwrap_or_default()
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|architecture| !architecture.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                architectures.sort();
+                architectures.dedup();
+                candidates
+                    .entry((
+                        source.source_type,
+                        normalize_uri(&source.uri),
+                        source.suite.clone(),
+                        architectures,
+                    ))
+                    .or_default()
+                    .push(source_ref);
+            }
+        }
+
+        let mut groups = Vec::new();
+        for ((source_type, uri, suite, _), refs) in candidates {
+            if refs.len() < 2 {
+                continue;
+            }
+            let mut seen = BTreeSet::new();
+            let mut shared = BTreeSet::new();
+            for source_ref in &refs {
+                let Some((_, source)) = self.get(*source_ref) else {
+                    continue;
+                };
+                for component in &source.components {
+                    if !seen.insert(component.clone()) {
+                        shared.insert(component.clone());
+                    }
+                }
+            }
+            // Exact path suites have no components, so any repeat is a duplicate
+            if shared.is_empty() && !suite.ends_with('/') {
+                continue;
+            }
+            groups.push(DuplicateGroup {
+                source_type,
+                uri,
+                suite,
+                components: shared.into_iter().collect(),
+                sources: refs,
+            });
+        }
+        groups
+    }
+
+    /// Keeps the source with the most components in each group and disables the others when
+    /// that loses nothing.
+    pub fn plan_consolidation(&self) -> ConsolidationPlan {
+        let mut plan = ConsolidationPlan::default();
+        for group in self.find_duplicates() {
+            let sources: Vec<(SourceRef, &AptSource)> = group
+                .sources
+                .iter()
+                .filter_map(|source_ref| {
+                    self.get(*source_ref)
+                        .map(|(_, source)| (*source_ref, source))
+                })
+                .collect();
+            // Ties go to the source apt reads first
+            let Some((keep_ref, keep)) = sources
+                .iter()
+                .rev()
+                .max_by_key(|(_, source)| source.components.len())
+                .copied()
+            else {
+                continue;
+            };
+            plan.keep.push(keep_ref);
+
+            for (source_ref, source) in sources {
+                if source_ref == keep_ref {
+                    continue;
+                }
+                let missing: Vec<&String> = source
+                    .components
+                    .iter()
+                    .filter(|component| !keep.components.contains(component))
+                    .collect();
+                if !missing.is_empty() {
+                    plan.skipped.push((
+                        source_ref,
+                        format!(
+                            "also has {}",
+                            missing
+                                .iter()
+                                .map(|c| c.as_str())
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        ),
+                    ));
+                } else {
+                    plan.disable.push(source_ref);
+                }
+            }
+        }
+
+        // Disabling a deb822 source disables its whole stanza, which is only safe when every
+        // source of the stanza is redundant.
+        let disable = plan.disable.clone();
+        let mut shared_stanza = Vec::new();
+        plan.disable.retain(|source_ref| {
+            let Some((apt_list, source)) = self.get(*source_ref) else {
+                return false;
+            };
+            let stanza_safe = apt_list
+                .sources
+                .iter()
+                .enumerate()
+                .filter(|(_, other)| other.line_number == source.line_number)
+                .all(|(index, _)| {
+                    disable.contains(&SourceRef {
+                        list: source_ref.list,
+                        source: index,
+                    })
+                });
+            if !stanza_safe {
+                shared_stanza.push((
+                    *source_ref,
+                    "shares a stanza with sources that aren't duplicates".to_string(),
+                ));
+            }
+            stanza_safe
+        });
+        plan.skipped.extend(shared_stanza);
+        plan
+    }
+
+    /// Disables the redundant sources of `plan` and reloads the lists.
+    pub fn apply_consolidation(&mut self, plan: &ConsolidationPlan) -> Result<usize> {
+        self.disable_sources(&plan.disable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::repositories;
+
+    #[test]
+    fn test_normalize_uri() {
+        assert_eq!(
+            normalize_uri("HTTP://user:pw@Deb.Debian.org/debian/"),
+            "http://deb.debian.org/debian"
+        );
+        assert_eq!(
+            normalize_uri("https://deb.debian.org/debian"),
+            "https://deb.debian.org/debian"
+        );
+    }
+
+    #[test]
+    fn test_find_duplicates_and_plan() {
+        let repositories = repositories()
+            .lines(
+                "/etc/apt/sources.list",
+                &[
+                    "deb http://deb.debian.org/debian bookworm main contrib",
+                    "deb http://deb.debian.org/debian bookworm-updates main",
+                ],
+            )
+            .lines(
+                "/etc/apt/sources.list.d/vendor.list",
+                &[
+                    "deb http://deb.debian.org/debian/ bookworm main",
+                    "deb http://deb.debian.org/debian bookworm-updates non-free",
+                    "# deb http://deb.debian.org/debian bookworm main",
+                ],
+            )
+            .lines(
+                "/etc/apt/sources.list.d/other.list",
+                &["deb http://deb.debian.org/debian bookworm main non-free"],
+            )
+            .lines(
+                "/etc/apt/sources.list.d/secure.list",
+                &["deb https://deb.debian.org/debian bookworm main"],
+            )
+            .lines(
+                "/etc/apt/sources.list.d/arm.list",
+                &[
+                    "deb [arch=arm64] http://deb.debian.org/debian bookworm-updates main",
+                    "deb [arch=arm64,armhf] http://deb.debian.org/debian bookworm main",
+                ],
+            )
+            .list(
+                "/etc/apt/sources.list.d/arm.sources",
+                "Types: deb\nURIs: http://deb.debian.org/debian\nSuites: bookworm-updates\nComponents: main\nArchitectures: arm64\n",
+            )
+            .build();
+
+        // The arm64 sources don't duplicate the unrestricted ones, only each other
+        let groups = repositories.find_duplicates();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].suite, "bookworm");
+        assert_eq!(groups[0].components, vec!["main"]);
+        assert_eq!(groups[0].sources.len(), 3);
+        assert_eq!(
+            groups[1].sources,
+            vec![
+                SourceRef { list: 4, source: 0 },
+                SourceRef { list: 5, source: 0 }
+            ]
+        );
+
+        let plan = repositories.plan_consolidation();
+        assert_eq!(
+            plan.keep,
+            vec![
+                SourceRef { list: 0, source: 0 },
+                SourceRef { list: 4, source: 0 }
+            ]
+        );
+        // Neither the unrestricted bookworm-updates sources nor the arm64,armhf one are
+        // touched
+        assert_eq!(
+            plan.disable,
+            vec![
+                SourceRef { list: 1, source: 0 },
+                SourceRef { list: 5, source: 0 }
+            ]
+        );
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].0, SourceRef { list: 2, source: 0 });
+    }
+}
@@ -0,0 +1,614 @@
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{
+    auth::mask_credentials,
+    deb822,
+    edit::{self, split_ending, split_lines},
+    keyrings::{
+        KeyringAudit, KeyringEntry, KeyringStatus, APT_KEYRINGS_PATH, SHARED_KEYRINGS_PATH,
+    },
+    legacy_keys::release_issuers,
+    lint::is_silently_ignored,
+    lists::APT_LISTS_PATH,
+    one_line, openpgp, sources_in_content, AptRepositories, SourceFormat, APT_SOURCES_LIST_D_PATH,
+};
+use crate::repositories::Repository;
+
+/// Mirrors that serve the same content over `https://`.
+const HTTPS_MIRRORS: [&str; 5] = [
+    "deb.debian.org",
+    "security.debian.org",
+    "archive.ubuntu.com",
+    "security.ubuntu.com",
+    "ports.ubuntu.com",
+];
+
+/// What a fix changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixKind {
+    /// Switches `uri` from `http://` to `https://`.
+    Https { uri: String },
+    /// Gives the file the extension apt reads.
+    Rename { to: PathBuf },
+    /// Removes entries that repeat an earlier line of the same file word for word.
+    RemoveDuplicateLines,
+    /// Single spaces between the words of an entry and no trailing whitespace.
+    NormalizeWhitespace,
+    /// Adds `signed-by` to the entry for `uri` and `suite`.
+    AddSignedBy {
+        uri: String,
+        suite: String,
+        keyring: PathBuf,
+    },
+}
+
+/// A suggested change to one source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub path: PathBuf,
+    pub format: SourceFormat,
+    pub kind: FixKind,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FixKind::Https { uri } => write!(f, "Use https for {}", mask_credentials(uri)),
+            FixKind::Rename { to } => write!(
+                f,
+                "Rename to {}, apt ignores the file now",
+                to.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            FixKind::RemoveDuplicateLines => write!(f, "Remove duplicate lines"),
+            FixKind::NormalizeWhitespace => write!(f, "Normalize whitespace"),
+            FixKind::AddSignedBy { uri, keyring, .. } => write!(
+                f,
+                "Sign {} with {}",
+                mask_credentials(uri),
+                keyring.display()
+            ),
+        }
+    }
+}
+
+impl Fix {
+    /// Short name of the kind of fix, for the list.
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            FixKind::Https { .. } => "https",
+            FixKind::Rename { .. } => "rename",
+            FixKind::RemoveDuplicateLines => "duplicates",
+            FixKind::NormalizeWhitespace => "whitespace",
+            FixKind::AddSignedBy { .. } => "signed-by",
+        }
+    }
+
+    /// `content` with the fix applied. A rename doesn't change the content.
+    fn apply_to(&self, content: &str) -> Result<String> {
+        match &self.kind {
+            FixKind::Https { uri } => {
+                let https = format!("https://{}", uri.trim_start_matches("http://"));
+                Ok(edit::replace_uri(content, uri, &https))
+            }
+            FixKind::Rename { .. } => Ok(content.to_string()),
+            FixKind::RemoveDuplicateLines => Ok(remove_duplicate_lines(content)),
+            FixKind::NormalizeWhitespace => Ok(normalize_whitespace(content, self.format)),
+            FixKind::AddSignedBy {
+                uri,
+                suite,
+                keyring,
+            } => {
+                let sources = sources_in_content(content, self.format)?;
+                let source = sources
+                    .iter()
+                    .find(|source| {
+                        source.uri == *uri
+                            && source.suite == *suite
+                            && source.option("signed-by").is_none()
+                    })
+                    .ok_or_else(|| eyre!("no entry for {uri} {suite} without signed-by"))?;
+                let keyring = keyring.to_string_lossy();
+                edit::set_option_in_content(content, self.format, source, "signed-by", &keyring)
+            }
+        }
+    }
+
+    /// What applying the fix would change in the file as it is now.
+    pub fn preview(&self) -> Result<Vec<DiffLine>> {
+        let content = fs::read_to_string(&self.path)
+            .map_err(|e| eyre!("Failed to read {}: {e}", self.path.display()))?;
+        let mut diff = diff_lines(&content, &self.apply_to(&content)?);
+        if let FixKind::Rename { to } = &self.kind {
+            diff.insert(0, DiffLine::Renamed(self.path.clone(), to.clone()));
+        }
+        Ok(diff)
+    }
+}
+
+/// Drops enabled one-line entries that repeat an earlier one.
+fn remove_duplicate_lines(content: &str) -> String {
+    let mut seen = HashSet::new();
+    split_lines(content)
+        .into_iter()
+        .filter(|line| {
+            let text = split_ending(line).0.trim();
+            let is_entry = one_line::parse_line(text, 1)
+                .ok()
+                .flatten()
+                .is_some_and(|source| source.enabled);
+            !is_entry || seen.insert(text.split_whitespace().collect::<Vec<_>>().join(" "))
+        })
+        .collect()
+}
+
+/// Collapses the whitespace inside entries and fields and trims it at the end of lines. The
+/// inside of `cdrom:[...]` URIs and the continuation lines of deb822 fields are kept.
+fn normalize_whitespace(content: &str, format: SourceFormat) -> String {
+    let mut normalized: String = split_lines(content)
+        .into_iter()
+        .map(|line| {
+            let (text, ending) = split_ending(&line);
+            let text = text.trim_end();
+            let words = || text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let text = match format {
+                SourceFormat::OneLine if text.contains("cdrom:") => text.to_string(),
+                SourceFormat::OneLine => match one_line::parse_line(text, 1) {
+                    Ok(Some(_)) => words(),
+                    _ => text.to_string(),
+                },
+                SourceFormat::Deb822 if text.starts_with([' ', '\t', '#']) => text.to_string(),
+                SourceFormat::Deb822 => match text.split_once(':') {
+                    Some((field, value)) if !value.trim().is_empty() => {
+                        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                        format!("{}: {value}", field.trim_end())
+                    }
+                    Some((field, _)) => format!("{}:", field.trim_end()),
+                    None => text.to_string(),
+                },
+            };
+            format!("{text}{ending}")
+        })
+        .collect();
+    if !normalized.is_empty() && !normalized.ends_with('\n') {
+        normalized.push('\n');
+    }
+    normalized
+}
+
+/// One changed line of a diff, numbered in the old file for removals and the new one for
+/// additions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Removed(usize, String),
+    Added(usize, String),
+    /// A file moved to another path.
+    Renamed(PathBuf, PathBuf),
+}
+
+/// The lines that differ between `old` and `new`, from their longest common subsequence.
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // common[i][j] is the length of the common subsequence of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(DiffLine::Removed(i + 1, old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(j + 1, new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// One change made by applying fixes, with what is needed to take it back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileChange {
+    Edit {
+        path: PathBuf,
+        before: String,
+        after: String,
+    },
+    Rename {
+        from: PathBuf,
+        to: PathBuf,
+    },
+}
+
+/// Fixes applied together, undone together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixRecord {
+    /// Unix time the fixes were applied at.
+    pub applied: u64,
+    pub fixes: Vec<String>,
+    pub changes: Vec<FileChange>,
+}
+
+/// Every applied fix, last one last, kept in the data directory so fixes can be undone after
+/// a restart.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixJournal {
+    pub records: Vec<FixRecord>,
+}
+
+impl FixJournal {
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| eyre!("Failed to read {}: {e}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(eyre!("Failed to read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        edit::write_atomic(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The extension apt would read `content` under, if it is a source file at all.
+fn detect_format(content: &str) -> Option<SourceFormat> {
+    let is_deb822 = deb822::parse_stanzas(content)
+        .is_ok_and(|stanzas| stanzas.iter().any(|stanza| stanza.get("Types").is_some()));
+    if is_deb822 {
+        return Some(SourceFormat::Deb822);
+    }
+    content
+        .lines()
+        .any(|line| one_line::parse_line(line, 1).ok().flatten().is_some())
+        .then_some(SourceFormat::OneLine)
+}
+
+/// Where a file of `format` with a name apt ignores should go, e.g. `vendor.list` for
+/// `vendor.lst` or `vendor.list.txt`.
+fn renamed_path(path: &Path, format: SourceFormat) -> PathBuf {
+    let extension = match format {
+        SourceFormat::OneLine => "list",
+        SourceFormat::Deb822 => "sources",
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    if stem.ends_with(&format!(".{extension}")) {
+        return path.with_file_name(stem.as_ref());
+    }
+    path.with_extension(extension)
+}
+
+impl AptRepositories {
+    /// Fixes for the problems that have an obvious solution. `audit` has the keyrings a
+    /// missing `signed-by` may point at.
+    pub fn plan_fixes(&self, audit: &KeyringAudit) -> Vec<Fix> {
+        let keyrings: Vec<&KeyringEntry> = audit
+            .keyrings
+            .iter()
+            .filter(|keyring| {
+                keyring.status != KeyringStatus::Missing
+                    && (keyring.path.starts_with(APT_KEYRINGS_PATH)
+                        || keyring.path.starts_with(SHARED_KEYRINGS_PATH))
+            })
+            .collect();
+        self.plan_fixes_in(
+            &keyrings,
+            Path::new(APT_LISTS_PATH),
+            Path::new(APT_SOURCES_LIST_D_PATH),
+        )
+    }
+
+    fn plan_fixes_in(
+        &self,
+        keyrings: &[&KeyringEntry],
+        lists_dir: &Path,
+        sources_dir: &Path,
+    ) -> Vec<Fix> {
+        let mut fixes = Vec::new();
+        for apt_list in &self.items {
+            let fix = |kind| Fix {
+                path: apt_list.file_path.clone(),
+                format: apt_list.format,
+                kind,
+            };
+            let Ok(content) = fs::read_to_string(&apt_list.file_path) else {
+                continue;
+            };
+            let mut https = Vec::new();
+            let mut signed = HashSet::new();
+            for source in apt_list.sources.iter().filter(|source| source.enabled) {
+                let host = source
+                    .uri
+                    .strip_prefix("http://")
+                    .and_then(|rest| rest.split('/').next());
+                if host.is_some_and(|host| HTTPS_MIRRORS.contains(&host))
+                    && !https.contains(&source.uri)
+                {
+                    https.push(source.uri.clone());
+                    fixes.push(fix(FixKind::Https {
+                        uri: source.uri.clone(),
+                    }));
+                }
+
+                // Once per deb822 stanza, which gets the field for all of its sources
+                if source.option("signed-by").is_some() || !signed.insert(source.line_number) {
+                    continue;
+                }
+                let Ok(issuers) = release_issuers(source, lists_dir) else {
+                    continue;
+                };
+                let matching: Vec<&&KeyringEntry> = keyrings
+                    .iter()
+                    .filter(|keyring| {
+                        keyring.keys.iter().any(|key| {
+                            issuers.iter().any(|issuer| key.matches_issuer(issuer))
+                                && !matches!(
+                                    key.expiry(openpgp::now()),
+                                    openpgp::KeyExpiry::Revoked
+                                )
+                        })
+                    })
+                    .collect();
+                if let [keyring] = matching.as_slice() {
+                    fixes.push(fix(FixKind::AddSignedBy {
+                        uri: source.uri.clone(),
+                        suite: source.suite.clone(),
+                        keyring: keyring.path.clone(),
+                    }));
+                }
+            }
+            if apt_list.format == SourceFormat::OneLine
+                && remove_duplicate_lines(&content) != content
+            {
+                fixes.push(fix(FixKind::RemoveDuplicateLines));
+            }
+            if normalize_whitespace(&content, apt_list.format) != content {
+                fixes.push(fix(FixKind::NormalizeWhitespace));
+            }
+        }
+
+        let mut paths: Vec<PathBuf> = match fs::read_dir(sources_dir) {
+            Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+        for path in paths {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !path.is_file()
+                || SourceFormat::from_path(&path).is_some()
+                || is_silently_ignored(&name)
+            {
+                continue;
+            }
+            let Some(format) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| detect_format(&content))
+            else {
+                continue;
+            };
+            let to = renamed_path(&path, format);
+            if !to.exists() {
+                fixes.push(Fix {
+                    path,
+                    format,
+                    kind: FixKind::Rename { to },
+                });
+            }
+        }
+        fixes
+    }
+
+    /// Applies `fixes`, several fixes of the same file in one write, records the changes in
+    /// the journal at `journal_path` and reloads the lists. Nothing is written when a fix
+    /// doesn't apply anymore.
+    pub fn apply_fixes(&mut self, fixes: &[Fix], journal_path: &Path) -> Result<usize> {
+        let mut by_path: BTreeMap<&Path, Vec<&Fix>> = BTreeMap::new();
+        for fix in fixes {
+            by_path.entry(&fix.path).or_default().push(fix);
+        }
+        let mut edits = Vec::new();
+        let mut renames = Vec::new();
+        for (path, fixes) in by_path {
+            let before = fs::read_to_string(path)
+                .map_err(|e| eyre!("Failed to read {}: {e}", path.display()))?;
+            let mut after = before.clone();
+            for fix in &fixes {
+                after = fix
+                    .apply_to(&after)
+                    .map_err(|e| eyre!("{}: {e}", path.display()))?;
+                if let FixKind::Rename { to } = &fix.kind {
+                    if to.exists() {
+                        return Err(eyre!("{} already exists", to.display()));
+                    }
+                    renames.push(FileChange::Rename {
+                        from: path.to_path_buf(),
+                        to: to.clone(),
+                    });
+                }
+            }
+            if after != before {
+                edits.push(FileChange::Edit {
+                    path: path.to_path_buf(),
+                    before,
+                    after,
+                });
+            }
+        }
+
+        let mut record = FixRecord {
+            applied: openpgp::now(),
+            fixes: fixes.iter().map(|fix| fix.to_string()).collect(),
+            changes: Vec::new(),
+        };
+        // Renames go last, so the edits find the files where they were planned
+        let result = edits.into_iter().chain(renames).try_for_each(|change| {
+            match &change {
+                FileChange::Edit { path, after, .. } => edit::write_atomic(path, after)?,
+                FileChange::Rename { from, to } => fs::rename(from, to)
+                    .map_err(|e| eyre!("Failed to rename {}: {e}", from.display()))?,
+            }
+            record.changes.push(change);
+            Ok(())
+        });
+        // Whatever made it to disk has to be undoable, even after a failure
+        if !record.changes.is_empty() {
+            let mut journal = FixJournal::load(journal_path)?;
+            journal.records.push(record);
+            journal.save(journal_path)?;
+        }
+        self.load_repository_list()?;
+        result.map(|_| fixes.len())
+    }
+
+    /// Takes back the last fixes recorded in the journal at `journal_path`, provided the files
+    /// weren't changed since. Returns what was undone.
+    pub fn undo_last_fix(&mut self, journal_path: &Path) -> Result<Vec<String>> {
+        let mut journal = FixJournal::load(journal_path)?;
+        let Some(record) = journal.records.pop() else {
+            return Err(eyre!("No fix to undo"));
+        };
+        for change in &record.changes {
+            match change {
+                FileChange::Edit { path, after, .. } => {
+                    if fs::read_to_string(path).ok().as_ref() != Some(after) {
+                        return Err(eyre!("{} changed since the fix", path.display()));
+                    }
+                }
+                FileChange::Rename { from, to } => {
+                    if from.exists() || !to.exists() {
+                        return Err(eyre!("{} was moved since the fix", to.display()));
+                    }
+                }
+            }
+        }
+        for change in record.changes.iter().rev() {
+            match change {
+                FileChange::Edit { path, before, .. } => edit::write_atomic(path, before)?,
+                FileChange::Rename { from, to } => fs::rename(to, from)
+                    .map_err(|e| eyre!("Failed to rename {}: {e}", to.display()))?,
+            }
+        }
+        journal.save(journal_path)?;
+        self.load_repository_list()?;
+        Ok(record.fixes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::{self, TempDir};
+
+    #[test]
+    fn test_normalize_whitespace() {
+        let content = "deb  [arch=amd64]\thttp://deb.debian.org/debian bookworm main  \n\
+                       # a   comment  \n\
+                       deb cdrom:[Debian  12]/ bookworm main";
+        assert_eq!(
+            normalize_whitespace(content, SourceFormat::OneLine),
+            "deb [arch=amd64] http://deb.debian.org/debian bookworm main\n\
+             # a   comment\n\
+             deb cdrom:[Debian  12]/ bookworm main\n"
+        );
+        let content =
+            "Types:\tdeb\nURIs:   http://deb.debian.org/debian \nSigned-By:\n -----BEGIN\n";
+        assert_eq!(
+            normalize_whitespace(content, SourceFormat::Deb822),
+            "Types: deb\nURIs: http://deb.debian.org/debian\nSigned-By:\n -----BEGIN\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            diff_lines("a\nb\nc\n", "a\nB\nc\nd\n"),
+            vec![
+                DiffLine::Removed(2, "b".to_string()),
+                DiffLine::Added(2, "B".to_string()),
+                DiffLine::Added(4, "d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_and_undo_fixes() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let dir = TempDir::new("fixes");
+        let sources_dir = dir.join("sources.list.d");
+        fs::create_dir_all(&sources_dir).unwrap();
+        let list_path = sources_dir.join("legacy.list");
+        let content = "\
+deb http://legacy.example/apt stable main
+deb http://deb.debian.org/debian bookworm main
+deb  http://deb.debian.org/debian bookworm main
+";
+        fs::write(&list_path, content).unwrap();
+        let misnamed = sources_dir.join("vendor.list.txt");
+        fs::write(&misnamed, "deb https://vendor.example/apt stable main\n").unwrap();
+
+        let mut repositories = test_support::load(&dir);
+        let keyring_path = fixtures.join("keyrings/legacy-trusted.gpg");
+        let keyring = KeyringEntry {
+            keys: openpgp::read_keyring(&keyring_path).unwrap(),
+            path: keyring_path.clone(),
+            status: KeyringStatus::Orphan,
+            used_by: Vec::new(),
+            package: None,
+            read_error: None,
+        };
+        let fixes = repositories.plan_fixes_in(&[&keyring], &fixtures.join("lists"), &sources_dir);
+        let kinds: Vec<&str> = fixes.iter().map(Fix::name).collect();
+        assert_eq!(
+            kinds,
+            vec!["signed-by", "https", "duplicates", "whitespace", "rename"]
+        );
+        assert_eq!(
+            fixes[4].kind,
+            FixKind::Rename {
+                to: sources_dir.join("vendor.list")
+            }
+        );
+
+        let journal_path = dir.join("journal.json");
+        repositories.apply_fixes(&fixes, &journal_path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&list_path).unwrap(),
+            format!(
+                "deb [signed-by={}] http://legacy.example/apt stable main\n\
+                 deb https://deb.debian.org/debian bookworm main\n",
+                keyring_path.display()
+            )
+        );
+        assert!(sources_dir.join("vendor.list").exists());
+
+        let undone = repositories.undo_last_fix(&journal_path).unwrap();
+        assert_eq!(undone.len(), 5);
+        assert_eq!(fs::read_to_string(&list_path).unwrap(), content);
+        assert!(misnamed.exists());
+        assert_eq!(
+            FixJournal::load(&journal_path).unwrap(),
+            FixJournal::default()
+        );
+    }
+}
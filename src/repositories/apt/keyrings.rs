@@ -0,0 +1,322 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{
+    openpgp::{self, KeyExpiry, PublicKey},
+    AptRepositories, SourceRef,
+};
+
+pub const APT_KEYRINGS_PATH: &str = "/etc/apt/keyrings";
+pub const SHARED_KEYRINGS_PATH: &str = "/usr/share/keyrings";
+/// Keys in this directory are trusted for every source without `signed-by`.
+pub const APT_TRUSTED_GPG_D_PATH: &str = "/etc/apt/trusted.gpg.d";
+/// The keyring `apt-key add` wrote to, trusted like the files in `trusted.gpg.d`.
+pub const LEGACY_TRUSTED_GPG_PATH: &str = "/etc/apt/trusted.gpg";
+const DPKG_INFO_PATH: &str = "/var/lib/dpkg/info";
+
+const KEYRING_DIRS: [&str; 3] = [
+    APT_KEYRINGS_PATH,
+    SHARED_KEYRINGS_PATH,
+    APT_TRUSTED_GPG_D_PATH,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyringStatus {
+    /// Referenced by a `signed-by` that points at a file that doesn't exist.
+    Missing,
+    /// Not referenced by any source and not owned by a package, safe to delete.
+    Orphan,
+    /// In `trusted.gpg.d`, trusted for every source without `signed-by`.
+    Global,
+    /// Referenced by at least one source.
+    Used,
+    /// Not referenced, but installed by a package, which should remove it.
+    Unused,
+}
+
+impl fmt::Display for KeyringStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing"),
+            Self::Orphan => write!(f, "orphan"),
+            Self::Global => write!(f, "global"),
+            Self::Used => write!(f, "used"),
+            Self::Unused => write!(f, "unused"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyringEntry {
+    pub path: PathBuf,
+    pub status: KeyringStatus,
+    /// Sources whose `signed-by` is this file.
+    pub used_by: Vec<SourceRef>,
+    /// The package that installed the file, from dpkg's file lists.
+    pub package: Option<String>,
+    pub keys: Vec<PublicKey>,
+    /// Why the keys couldn't be read, for files that exist.
+    pub read_error: Option<String>,
+}
+
+impl KeyringEntry {
+    /// The key that stays valid longest, which is the one that matters: apt accepts a
+    /// signature by any key in the file.
+    pub fn longest_valid_key(&self, now: u64) -> Option<&PublicKey> {
+        self.keys.iter().max_by_key(|key| key.expiry(now))
+    }
+
+    pub fn expiry(&self, now: u64) -> Option<KeyExpiry> {
+        self.longest_valid_key(now).map(|key| key.expiry(now))
+    }
+}
+
+/// How the loaded sources are authenticated.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KeyringAudit {
+    /// Problems first, then the files that are fine.
+    pub keyrings: Vec<KeyringEntry>,
+    /// Enabled sources without `signed-by`, which trust every key in `trusted.gpg.d`.
+    pub unsigned: Vec<SourceRef>,
+}
+
+impl KeyringAudit {
+    pub fn keyring_of(&self, source_ref: SourceRef) -> Option<&KeyringEntry> {
+        self.keyrings
+            .iter()
+            .find(|keyring| keyring.used_by.contains(&source_ref))
+    }
+
+    pub fn is_orphan(&self, path: &Path) -> bool {
+        self.keyrings
+            .iter()
+            .any(|keyring| keyring.path == path && keyring.status == KeyringStatus::Orphan)
+    }
+}
+
+/// Keyring files found in `dirs`. Other files, such as a README, are left out.
+fn keyring_files(dirs: &[&str]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        files.extend(entries.flatten().map(|entry| entry.path()).filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|extension| extension == "gpg" || extension == "asc")
+        }));
+    }
+    files.sort();
+    files
+}
+
+/// The package owning each file under the keyring directories.
+fn package_owners() -> BTreeMap<PathBuf, String> {
+    let mut owners = BTreeMap::new();
+    let Ok(entries) = fs::read_dir(DPKG_INFO_PATH) else {
+        return owners;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().is_none_or(|extension| extension != "list") {
+            continue;
+        }
+        let Some(package) = path.file_stem().map(|stem| stem.to_string_lossy()) else {
+            continue;
+        };
+        // Multi-arch packages are listed as `name:arch`
+        let package = package.split(':').next().unwrap_or_default().to_string();
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in content.lines() {
+            if KEYRING_DIRS
+                .iter()
+                .any(|dir| Path::new(line).starts_with(dir))
+            {
+                owners.insert(PathBuf::from(line), package.clone());
+            }
+        }
+    }
+    owners
+}
+
+impl AptRepositories {
+    /// Cross-references the `signed-by` of every source with the keyring directories.
+    pub fn audit_keyrings(&self) -> KeyringAudit {
+        let mut files = keyring_files(&KEYRING_DIRS);
+        if Path::new(LEGACY_TRUSTED_GPG_PATH).is_file() {
+            files.push(PathBuf::from(LEGACY_TRUSTED_GPG_PATH));
+        }
+        let mut audit = self.audit_keyring_files(&files, &package_owners());
+        for keyring in &mut audit.keyrings {
+            if keyring.status == KeyringStatus::Missing {
+                continue;
+            }
+            match openpgp::read_keyring(&keyring.path) {
+                Ok(keys) => keyring.keys = keys,
+                Err(e) => keyring.read_error = Some(e.to_string()),
+            }
+        }
+        audit
+    }
+
+    fn audit_keyring_files(
+        &self,
+        files: &[PathBuf],
+        owners: &BTreeMap<PathBuf, String>,
+    ) -> KeyringAudit {
+        let mut used_by: BTreeMap<PathBuf, Vec<SourceRef>> = BTreeMap::new();
+        let mut unsigned = Vec::new();
+        for (source_ref, _, source) in self.source_refs() {
+            match (source.option("signed-by"), source.signed_by_path()) {
+                (_, Some(path)) => used_by
+                    .entry(PathBuf::from(path))
+                    .or_default()
+                    .push(source_ref),
+                (None, None) if source.enabled => unsigned.push(source_ref),
+                // Fingerprints and inline keys don't need a file
+                _ => {}
+            }
+        }
+
+        let mut keyrings: Vec<KeyringEntry> = used_by
+            .iter()
+            .filter(|(path, _)| !files.contains(path) && !path.is_file())
+            .map(|(path, sources)| KeyringEntry {
+                path: path.clone(),
+                status: KeyringStatus::Missing,
+                used_by: sources.clone(),
+                package: None,
+                keys: Vec::new(),
+                read_error: None,
+            })
+            .collect();
+        let mut listed: Vec<&PathBuf> = files.iter().collect();
+        // Keyrings outside the usual directories are still worth listing when they are used
+        listed.extend(
+            used_by
+                .keys()
+                .filter(|path| !files.contains(path) && path.is_file()),
+        );
+        for path in listed {
+            let sources = used_by.get(path).cloned().unwrap_or_default();
+            let package = owners.get(path).cloned();
+            let status = if !sources.is_empty() {
+                KeyringStatus::Used
+            } else if (path.starts_with(APT_TRUSTED_GPG_D_PATH) || path == LEGACY_TRUSTED_GPG_PATH)
+                && !unsigned.is_empty()
+            {
+                KeyringStatus::Global
+            } else if package.is_some() {
+                KeyringStatus::Unused
+            } else {
+                KeyringStatus::Orphan
+            };
+            keyrings.push(KeyringEntry {
+                path: path.clone(),
+                status,
+                used_by: sources,
+                package,
+                keys: Vec::new(),
+                read_error: None,
+            });
+        }
+        keyrings.sort_by(|a, b| (a.status, &a.path).cmp(&(b.status, &b.path)));
+
+        KeyringAudit { keyrings, unsigned }
+    }
+
+    /// Deletes a keyring file, but only one the audit reports as an orphan.
+    pub fn delete_keyring(&self, path: &Path) -> Result<()> {
+        if !self.audit_keyrings().is_orphan(path) {
+            return Err(eyre!("{} is not an orphaned keyring", path.display()));
+        }
+        fs::remove_file(path).map_err(|e| eyre!("Failed to delete {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::repositories;
+
+    #[test]
+    fn test_audit_keyring_files() {
+        let repositories = repositories()
+            .lines(
+                "/etc/apt/sources.list.d/vendor.list",
+                &[
+                    "deb [signed-by=/etc/apt/keyrings/vendor.gpg] https://vendor.example/apt stable main",
+                    "deb [signed-by=/etc/apt/keyrings/gone.gpg] https://gone.example/apt stable main",
+                    "deb https://unsigned.example/apt stable main",
+                    "# deb https://disabled.example/apt stable main",
+                ],
+            )
+            .build();
+        let files = [
+            "/etc/apt/keyrings/vendor.gpg",
+            "/etc/apt/keyrings/removed-vendor.gpg",
+            "/etc/apt/trusted.gpg.d/debian-archive-bookworm-stable.asc",
+            "/usr/share/keyrings/debian-archive-removed-keys.gpg",
+        ]
+        .map(PathBuf::from);
+        let owners = BTreeMap::from([(
+            PathBuf::from("/usr/share/keyrings/debian-archive-removed-keys.gpg"),
+            "debian-archive-keyring".to_string(),
+        )]);
+
+        let audit = repositories.audit_keyring_files(&files, &owners);
+        let statuses: Vec<(&str, KeyringStatus)> = audit
+            .keyrings
+            .iter()
+            .map(|keyring| (keyring.path.to_str().unwrap(), keyring.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("/etc/apt/keyrings/gone.gpg", KeyringStatus::Missing),
+                (
+                    "/etc/apt/keyrings/removed-vendor.gpg",
+                    KeyringStatus::Orphan
+                ),
+                (
+                    "/etc/apt/trusted.gpg.d/debian-archive-bookworm-stable.asc",
+                    KeyringStatus::Global
+                ),
+                ("/etc/apt/keyrings/vendor.gpg", KeyringStatus::Used),
+                (
+                    "/usr/share/keyrings/debian-archive-removed-keys.gpg",
+                    KeyringStatus::Unused
+                ),
+            ]
+        );
+        assert_eq!(audit.unsigned, vec![SourceRef { list: 0, source: 2 }]);
+        assert!(audit.is_orphan(Path::new("/etc/apt/keyrings/removed-vendor.gpg")));
+    }
+
+    #[test]
+    fn test_keyring_expiry_follows_subkeys() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/keyrings/example-release.asc");
+        let keyring = KeyringEntry {
+            keys: openpgp::read_keyring(&path).unwrap(),
+            path,
+            status: KeyringStatus::Used,
+            used_by: Vec::new(),
+            package: None,
+            read_error: None,
+        };
+        // The primary key is valid until 2027, its only subkey expired at the start of 2026
+        assert_eq!(
+            keyring.expiry(openpgp::now()),
+            Some(KeyExpiry::Expired(1767268800))
+        );
+    }
+}
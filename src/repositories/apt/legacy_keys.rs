@@ -0,0 +1,389 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{
+    convert::backup_path,
+    edit,
+    keyrings::{APT_KEYRINGS_PATH, LEGACY_TRUSTED_GPG_PATH},
+    lists::APT_LISTS_PATH,
+    openpgp::{self, PublicKey},
+    AptRepositories, AptSource, SourceRef,
+};
+use crate::repositories::Repository;
+
+/// A key of `trusted.gpg` that gets a keyring of its own.
+#[derive(Debug, Default, Clone)]
+pub struct KeyMove {
+    pub key: PublicKey,
+    /// The packets of the key, written out as the new keyring.
+    data: Vec<u8>,
+    pub keyring_path: PathBuf,
+    /// Sources whose cached Release files are signed by the key.
+    pub sources: Vec<SourceRef>,
+}
+
+/// A source file that gets `signed-by` options.
+#[derive(Debug, Default, Clone)]
+pub struct SourceFileChange {
+    pub file_path: PathBuf,
+    pub backup_path: PathBuf,
+    pub original: String,
+    pub updated: String,
+}
+
+/// Moving the keys of the legacy `trusted.gpg` to keyrings named by the sources they sign.
+#[derive(Debug, Default, Clone)]
+pub struct LegacyKeyMigration {
+    pub trusted_path: PathBuf,
+    pub backup_path: PathBuf,
+    original: Vec<u8>,
+    pub moves: Vec<KeyMove>,
+    /// Keys no source was found for. They stay in `trusted.gpg`, which is removed when
+    /// nothing is left.
+    pub kept: Vec<PublicKey>,
+    kept_data: Vec<u8>,
+    /// Enabled sources without `signed-by` whose signer couldn't be told, with the reason.
+    pub unmatched: Vec<(SourceRef, String)>,
+    pub files: Vec<SourceFileChange>,
+}
+
+impl LegacyKeyMigration {
+    /// Whether `trusted.gpg` is left as it is. The unmatched sources may need any of its keys,
+    /// so the moved keys are only copied to their keyrings until those sources are sorted out.
+    pub fn keeps_trusted(&self) -> bool {
+        !self.unmatched.is_empty()
+    }
+}
+
+/// The key IDs or fingerprints that signed the cached Release file of `source`, or why they
+/// are unknown.
+pub(super) fn release_issuers(source: &AptSource, lists_dir: &Path) -> Result<Vec<String>, String> {
+    let Some(path) = source
+        .release_signature_paths(lists_dir)
+        .into_iter()
+        .find(|path| path.is_file())
+    else {
+        return Err("no Release file in the apt lists, run apt update first".to_string());
+    };
+    fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| openpgp::signature_issuers(&data).map_err(|e| e.to_string()))
+        .map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// The file name for the keyring of a key that signs `source`: the name of its list, or its
+/// host for the main `sources.list`.
+fn keyring_name(list_path: &Path, primary: bool, source: &AptSource) -> String {
+    let stem = list_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|_| !primary);
+    stem.unwrap_or_else(|| {
+        source
+            .uri
+            .split_once("://")
+            .map_or(source.uri.as_str(), |(_, rest)| rest)
+            .split(['/', ':', '@'])
+            .find(|part| !part.is_empty())
+            .unwrap_or("legacy")
+            .to_string()
+    })
+}
+
+impl AptRepositories {
+    /// Matches the keys of `/etc/apt/trusted.gpg` to the sources they sign and computes the
+    /// new keyrings and source files, without writing anything.
+    pub fn plan_key_migration(&self) -> Result<LegacyKeyMigration> {
+        let trusted_path = PathBuf::from(LEGACY_TRUSTED_GPG_PATH);
+        let original = fs::read(&trusted_path)
+            .map_err(|e| eyre!("Unable to read {}: {e}", trusted_path.display()))?;
+        let mut plan = self.plan_key_moves(
+            &original,
+            Path::new(APT_LISTS_PATH),
+            Path::new(APT_KEYRINGS_PATH),
+        )?;
+        plan.backup_path = backup_path(&trusted_path);
+        plan.trusted_path = trusted_path;
+
+        // Bottom up, so the line numbers of the remaining sources stay valid when a deb822
+        // stanza gets a new line
+        let mut by_file: BTreeMap<usize, Vec<(&AptSource, &Path)>> = BTreeMap::new();
+        for key_move in &plan.moves {
+            for source_ref in &key_move.sources {
+                if let Some((_, source)) = self.get(*source_ref) {
+                    by_file
+                        .entry(source_ref.list)
+                        .or_default()
+                        .push((source, &key_move.keyring_path));
+                }
+            }
+        }
+        let mut files = Vec::new();
+        for (list_index, mut sources) in by_file {
+            let apt_list = &self.items[list_index];
+            sources.sort_by_key(|(source, _)| std::cmp::Reverse(source.line_number));
+            sources.dedup_by_key(|(source, _)| source.line_number);
+            let original = fs::read_to_string(&apt_list.file_path)?;
+            let mut updated = original.clone();
+            for (source, keyring_path) in sources {
+                updated = edit::set_option_in_content(
+                    &updated,
+                    apt_list.format,
+                    source,
+                    "signed-by",
+                    &keyring_path.to_string_lossy(),
+                )
+                .map_err(|e| eyre!("{}: {e}", apt_list.file_path.display()))?;
+            }
+            files.push(SourceFileChange {
+                backup_path: backup_path(&apt_list.file_path),
+                file_path: apt_list.file_path.clone(),
+                original,
+                updated,
+            });
+        }
+        plan.files = files;
+        Ok(plan)
+    }
+
+    /// Splits `trusted` into its keys and finds the sources without `signed-by` each of them
+    /// signs, going by the Release files cached in `lists_dir`.
+    fn plan_key_moves(
+        &self,
+        trusted: &[u8],
+        lists_dir: &Path,
+        keyrings_dir: &Path,
+    ) -> Result<LegacyKeyMigration> {
+        let keys = openpgp::split_keyring(trusted)?;
+        let mut plan = LegacyKeyMigration {
+            original: trusted.to_vec(),
+            ..LegacyKeyMigration::default()
+        };
+
+        // Every source of a deb822 stanza gets the same signed-by, so they are matched together
+        let mut stanzas: BTreeMap<(usize, usize), Vec<SourceRef>> = BTreeMap::new();
+        for (source_ref, _, source) in self.source_refs() {
+            if source.option("signed-by").is_none() {
+                stanzas
+                    .entry((source_ref.list, source.line_number))
+                    .or_default()
+                    .push(source_ref);
+            }
+        }
+
+        let mut signed_by: BTreeMap<usize, Vec<SourceRef>> = BTreeMap::new();
+        for source_refs in stanzas.values() {
+            let mut stanza_keys = Vec::new();
+            for source_ref in source_refs {
+                let Some((_, source)) = self.get(*source_ref) else {
+                    continue;
+                };
+                match release_issuers(source, lists_dir) {
+                    Ok(issuers) => stanza_keys.extend(keys.iter().position(|(key, _)| {
+                        issuers.iter().any(|issuer| key.matches_issuer(issuer))
+                    })),
+                    Err(reason) if source.enabled => plan.unmatched.push((*source_ref, reason)),
+                    Err(_) => {}
+                }
+            }
+            stanza_keys.sort();
+            stanza_keys.dedup();
+            match stanza_keys.as_slice() {
+                // Signed by a key from somewhere else, such as trusted.gpg.d
+                [] => {}
+                [key_index] => signed_by.entry(*key_index).or_default().extend(source_refs),
+                _ => plan.unmatched.extend(source_refs.iter().map(|source_ref| {
+                    (
+                        *source_ref,
+                        "its entry has repositories signed by different keys".to_string(),
+                    )
+                })),
+            }
+        }
+
+        for (index, (key, data)) in keys.into_iter().enumerate() {
+            let Some(sources) = signed_by.remove(&index) else {
+                plan.kept.push(key);
+                plan.kept_data.extend(data);
+                continue;
+            };
+            let name = sources
+                .first()
+                .and_then(|source_ref| self.get(*source_ref))
+                .map(|(apt_list, source)| {
+                    keyring_name(&apt_list.file_path, apt_list.primary, source)
+                })
+                .unwrap_or_else(|| "legacy".to_string());
+            let mut keyring_path = keyrings_dir.join(format!("{name}.gpg"));
+            let taken = |path: &Path, moves: &[KeyMove]| {
+                path.exists() || moves.iter().any(|other| other.keyring_path == path)
+            };
+            if taken(&keyring_path, &plan.moves) {
+                let suffix = &key.fingerprint[key.fingerprint.len().saturating_sub(8)..];
+                keyring_path = keyrings_dir.join(format!("{name}-{}.gpg", suffix.to_lowercase()));
+            }
+            plan.moves.push(KeyMove {
+                key,
+                data,
+                keyring_path,
+                sources,
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Backs up `trusted.gpg` and the source files, writes the new keyrings and sources, and
+    /// leaves only the unmatched keys in `trusted.gpg`, or all of them while a source is
+    /// unmatched. Nothing is written when a file changed since the plan was made.
+    pub fn apply_key_migration(&mut self, plan: &LegacyKeyMigration) -> Result<usize> {
+        if plan.moves.is_empty() {
+            return Err(eyre!(
+                "No key of {} signs a source",
+                plan.trusted_path.display()
+            ));
+        }
+        if fs::read(&plan.trusted_path)? != plan.original {
+            return Err(eyre!(
+                "{} changed since the preview",
+                plan.trusted_path.display()
+            ));
+        }
+        for file in &plan.files {
+            if fs::read_to_string(&file.file_path)? != file.original {
+                return Err(eyre!(
+                    "{} changed since the preview",
+                    file.file_path.display()
+                ));
+            }
+        }
+        let new_paths = std::iter::once(&plan.backup_path)
+            .filter(|_| !plan.keeps_trusted())
+            .chain(plan.files.iter().map(|file| &file.backup_path))
+            .chain(plan.moves.iter().map(|key_move| &key_move.keyring_path));
+        for path in new_paths {
+            if path.exists() {
+                return Err(eyre!("{} already exists", path.display()));
+            }
+        }
+
+        let copy = |from: &Path, to: &Path| {
+            fs::copy(from, to)
+                .map(|_| ())
+                .map_err(|e| eyre!("Failed to back up {}: {e}", from.display()))
+        };
+        if !plan.keeps_trusted() {
+            copy(&plan.trusted_path, &plan.backup_path)?;
+        }
+        for file in &plan.files {
+            copy(&file.file_path, &file.backup_path)?;
+        }
+        for key_move in &plan.moves {
+            if let Some(dir) = key_move.keyring_path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            edit::write_atomic(&key_move.keyring_path, &key_move.data)?;
+        }
+        for file in &plan.files {
+            edit::write_atomic(&file.file_path, &file.updated)?;
+        }
+        if plan.keeps_trusted() {
+            // Left for the unmatched sources
+        } else if plan.kept.is_empty() {
+            fs::remove_file(&plan.trusted_path)?;
+        } else {
+            edit::write_atomic(&plan.trusted_path, &plan.kept_data)?;
+        }
+        self.load_repository_list()?;
+        Ok(plan.moves.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::{self, repositories, TempDir};
+
+    #[test]
+    fn test_plan_key_moves() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let repositories = repositories()
+            .lines(
+                "/etc/apt/sources.list.d/legacy.list",
+                &[
+                    "deb http://legacy.example/apt stable main",
+                    "deb https://uncached.example/apt stable main",
+                    "deb [signed-by=/etc/apt/keyrings/signed.gpg] https://signed.example/apt stable main",
+                ],
+            )
+            .build();
+        let trusted = fs::read(fixtures.join("keyrings/legacy-trusted.gpg")).unwrap();
+
+        let plan = repositories
+            .plan_key_moves(
+                &trusted,
+                &fixtures.join("lists"),
+                Path::new("/nonexistent/keyrings"),
+            )
+            .unwrap();
+        assert_eq!(plan.moves.len(), 1);
+        assert_eq!(
+            plan.moves[0].key.fingerprint,
+            "DD9A1B7444ECC52DA94A90F8470097D451DB7E15"
+        );
+        assert_eq!(
+            plan.moves[0].keyring_path,
+            PathBuf::from("/nonexistent/keyrings/legacy.gpg")
+        );
+        assert_eq!(
+            plan.moves[0].sources,
+            vec![SourceRef { list: 0, source: 0 }]
+        );
+        assert_eq!(
+            openpgp::parse_keyring(&plan.moves[0].data).unwrap(),
+            vec![plan.moves[0].key.clone()]
+        );
+        assert_eq!(plan.kept.len(), 1);
+        assert_eq!(plan.kept[0].name(), "Expired Vendor <keys@expired.example>");
+        assert_eq!(
+            plan.unmatched
+                .iter()
+                .map(|(source_ref, _)| *source_ref)
+                .collect::<Vec<_>>(),
+            vec![SourceRef { list: 0, source: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_unmatched_sources_keep_trusted() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let dir = TempDir::new("legacy-keys");
+        fs::create_dir(dir.join("sources.list.d")).unwrap();
+        fs::write(
+            dir.join("sources.list.d/legacy.list"),
+            "deb http://legacy.example/apt stable main\ndeb https://uncached.example/apt stable main\n",
+        )
+        .unwrap();
+        let mut repositories = test_support::load(&dir);
+        let trusted_path = dir.join("trusted.gpg");
+        fs::copy(fixtures.join("keyrings/legacy-trusted.gpg"), &trusted_path).unwrap();
+        let trusted = fs::read(&trusted_path).unwrap();
+        let mut plan = repositories
+            .plan_key_moves(&trusted, &fixtures.join("lists"), &dir.join("keyrings"))
+            .unwrap();
+        plan.backup_path = backup_path(&trusted_path);
+        plan.trusted_path = trusted_path.clone();
+        assert!(plan.keeps_trusted());
+
+        repositories.apply_key_migration(&plan).unwrap();
+        assert_eq!(fs::read(&trusted_path).unwrap(), trusted);
+        assert!(!plan.backup_path.exists());
+        assert_eq!(
+            openpgp::read_keyring(&plan.moves[0].keyring_path).unwrap(),
+            vec![plan.moves[0].key.clone()]
+        );
+    }
+}
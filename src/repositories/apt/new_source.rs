@@ -0,0 +1,429 @@
+use color_eyre::{eyre::eyre, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{
+    edit::write_atomic,
+    keyrings::{APT_KEYRINGS_PATH, SHARED_KEYRINGS_PATH},
+    openpgp,
+    ppa::Ppa,
+    AptRepositories, SourceFormat, SourceType, APT_SOURCES_LIST_D_PATH,
+};
+use crate::repositories::Repository;
+
+/// The fields of the add source form, as typed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NewSourceField {
+    #[default]
+    Type,
+    Uri,
+    Suite,
+    Components,
+    Architectures,
+    SignedBy,
+}
+
+/// A source the user is about to add. Values are kept as typed so they can be validated while
+/// the form is being filled in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewSource {
+    pub source_type: String,
+    pub uri: String,
+    pub suite: String,
+    pub components: String,
+    pub architectures: String,
+    pub signed_by: String,
+    /// The PPA the URI was expanded from, which names the files like add-apt-repository.
+    pub ppa: Option<Ppa>,
+}
+
+impl Default for NewSource {
+    fn default() -> Self {
+        Self {
+            source_type: "deb".to_string(),
+            uri: String::new(),
+            suite: String::new(),
+            components: "main".to_string(),
+            architectures: String::new(),
+            signed_by: String::new(),
+            ppa: None,
+        }
+    }
+}
+
+fn split_list(value: &str) -> Vec<&str> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl NewSource {
+    pub fn get(&self, field: NewSourceField) -> &str {
+        match field {
+            NewSourceField::Type => &self.source_type,
+            NewSourceField::Uri => &self.uri,
+            NewSourceField::Suite => &self.suite,
+            NewSourceField::Components => &self.components,
+            NewSourceField::Architectures => &self.architectures,
+            NewSourceField::SignedBy => &self.signed_by,
+        }
+    }
+
+    pub fn get_mut(&mut self, field: NewSourceField) -> &mut String {
+        match field {
+            NewSourceField::Type => &mut self.source_type,
+            NewSourceField::Uri => &mut self.uri,
+            NewSourceField::Suite => &mut self.suite,
+            NewSourceField::Components => &mut self.components,
+            NewSourceField::Architectures => &mut self.architectures,
+            NewSourceField::SignedBy => &mut self.signed_by,
+        }
+    }
+
+    /// `ppa:owner/name` in the URI expanded to the Launchpad URI, with the suite defaulting to
+    /// `codename`, the Ubuntu release the PPA is built for. Other sources are left as they are.
+    pub fn expand_ppa(mut self, codename: Option<&str>) -> Self {
+        let Ok(ppa) = Ppa::parse(&self.uri) else {
+            return self;
+        };
+        self.uri = ppa.uri();
+        if self.suite.trim().is_empty() {
+            self.suite = codename.unwrap_or_default().to_string();
+        }
+        self.ppa = Some(ppa);
+        self
+    }
+
+    /// Where the keyring of a PPA is copied to, unless it is already in a keyring directory.
+    /// Armored keys keep the `.asc` extension apt needs to read them.
+    pub fn keyring_destination(&self) -> Option<PathBuf> {
+        let ppa = self.ppa.as_ref()?;
+        let signed_by = Path::new(self.signed_by.trim());
+        if signed_by.as_os_str().is_empty()
+            || signed_by.starts_with(APT_KEYRINGS_PATH)
+            || signed_by.starts_with(SHARED_KEYRINGS_PATH)
+        {
+            return None;
+        }
+        let armored = fs::read(signed_by)
+            .is_ok_and(|data| data.trim_ascii_start().starts_with(b"-----BEGIN PGP"));
+        let extension = if armored { "asc" } else { "gpg" };
+        Some(Path::new(APT_KEYRINGS_PATH).join(format!("{}.{extension}", ppa.keyring_stem())))
+    }
+
+    /// Problems with the current values, at most one per field.
+    pub fn validate(&self) -> Vec<(NewSourceField, String)> {
+        let mut errors = Vec::new();
+
+        if SourceType::parse(self.source_type.trim()).is_none() {
+            errors.push((NewSourceField::Type, "must be deb or deb-src".to_string()));
+        }
+
+        let uri = self.uri.trim();
+        if uri.is_empty() {
+            errors.push((NewSourceField::Uri, "required".to_string()));
+        } else if Ppa::is_shorthand(uri) {
+            // A valid shorthand is expanded before it gets here
+            let error = Ppa::parse(uri).err().unwrap_or("not expanded to a URI");
+            errors.push((NewSourceField::Uri, error.to_string()));
+        } else if uri.contains(char::is_whitespace) {
+            errors.push((NewSourceField::Uri, "can't contain spaces".to_string()));
+        } else if !uri.split_once(':').is_some_and(|(scheme, rest)| {
+            !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+')
+                && !rest.is_empty()
+        }) {
+            errors.push((
+                NewSourceField::Uri,
+                "must start with a scheme such as https://".to_string(),
+            ));
+        }
+
+        let suite = self.suite.trim();
+        if suite.is_empty() {
+            errors.push((NewSourceField::Suite, "required".to_string()));
+        } else if suite.contains(char::is_whitespace) {
+            errors.push((
+                NewSourceField::Suite,
+                "only one suite per source".to_string(),
+            ));
+        }
+
+        let components = split_list(&self.components);
+        if suite.ends_with('/') && !components.is_empty() {
+            errors.push((
+                NewSourceField::Components,
+                "must be empty for an exact path suite".to_string(),
+            ));
+        } else if !suite.ends_with('/') && components.is_empty() {
+            errors.push((NewSourceField::Components, "required".to_string()));
+        }
+
+        if split_list(&self.architectures).iter().any(|arch| {
+            !arch
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        }) {
+            errors.push((
+                NewSourceField::Architectures,
+                "use names such as amd64 or arm64".to_string(),
+            ));
+        }
+
+        let signed_by = self.signed_by.trim();
+        if signed_by.is_empty() && self.ppa.is_some() {
+            // There is no network to fetch the key from Launchpad
+            errors.push((
+                NewSourceField::SignedBy,
+                "required for a PPA, the path of its key downloaded from Launchpad".to_string(),
+            ));
+        } else if !signed_by.is_empty() {
+            if !signed_by.starts_with('/') {
+                errors.push((
+                    NewSourceField::SignedBy,
+                    "must be an absolute path".to_string(),
+                ));
+            } else if !Path::new(signed_by).is_file() {
+                errors.push((NewSourceField::SignedBy, "file not found".to_string()));
+            } else if self.ppa.is_some() && openpgp::read_keyring(Path::new(signed_by)).is_err() {
+                errors.push((
+                    NewSourceField::SignedBy,
+                    "not an OpenPGP keyring".to_string(),
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Name of the file the source is written to, derived from the URI, or named the way
+    /// add-apt-repository names it for a PPA.
+    pub fn file_name(&self, format: SourceFormat) -> String {
+        let extension = match format {
+            SourceFormat::OneLine => "list",
+            SourceFormat::Deb822 => "sources",
+        };
+        if let Some(ppa) = &self.ppa {
+            return format!("{}.{extension}", ppa.file_stem(self.suite.trim()));
+        }
+        let uri = self.uri.trim();
+        let without_scheme = uri.split_once("://").map_or(uri, |(_, rest)| rest);
+        let without_auth = without_scheme
+            .split_once('@')
+            .map_or(without_scheme, |(_, rest)| rest);
+        let mut name = String::new();
+        for c in without_auth.chars() {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                name.push(c);
+            } else if !name.ends_with('-') {
+                name.push('-');
+            }
+        }
+        let name = name.trim_matches('-');
+        let name = if name.is_empty() { "custom" } else { name };
+        format!("{name}.{extension}")
+    }
+
+    /// The text of the new file.
+    pub fn render(&self, format: SourceFormat) -> String {
+        let source_type = self.source_type.trim();
+        let uri = self.uri.trim();
+        let suite = self.suite.trim();
+        let components = split_list(&self.components);
+        let architectures = split_list(&self.architectures);
+        let keyring = self.keyring_destination();
+        let signed_by = keyring
+            .as_deref()
+            .map_or(self.signed_by.trim().to_string(), |keyring| {
+                keyring.display().to_string()
+            });
+
+        match format {
+            SourceFormat::OneLine => {
+                let mut options = Vec::new();
+                if !architectures.is_empty() {
+                    options.push(format!("arch={}", architectures.join(",")));
+                }
+                if !signed_by.is_empty() {
+                    options.push(format!("signed-by={signed_by}"));
+                }
+                let mut line = source_type.to_string();
+                if !options.is_empty() {
+                    line.push_str(&format!(" [{}]", options.join(" ")));
+                }
+                line.push_str(&format!(" {uri} {suite}"));
+                for component in components {
+                    line.push_str(&format!(" {component}"));
+                }
+                line.push('\n');
+                line
+            }
+            SourceFormat::Deb822 => {
+                let mut stanza = format!("Types: {source_type}\nURIs: {uri}\nSuites: {suite}\n");
+                if !components.is_empty() {
+                    stanza.push_str(&format!("Components: {}\n", components.join(" ")));
+                }
+                if !architectures.is_empty() {
+                    stanza.push_str(&format!("Architectures: {}\n", architectures.join(" ")));
+                }
+                if !signed_by.is_empty() {
+                    stanza.push_str(&format!("Signed-By: {signed_by}\n"));
+                }
+                stanza
+            }
+        }
+    }
+}
+
+impl AptRepositories {
+    /// Writes `source` to a new file in `sources.list.d` and reloads the lists. Existing files
+    /// are never overwritten.
+    pub fn add_source(&mut self, source: &NewSource, format: SourceFormat) -> Result<PathBuf> {
+        if let Some((_, error)) = source.validate().first() {
+            return Err(eyre!("Invalid source: {error}"));
+        }
+        let path = Path::new(APT_SOURCES_LIST_D_PATH).join(source.file_name(format));
+        if path.exists() {
+            return Err(eyre!("{} already exists", path.display()));
+        }
+        // The keyring is only removed again if it was written here
+        let mut new_keyring = None;
+        if let Some(keyring) = source.keyring_destination() {
+            let key = fs::read(source.signed_by.trim())?;
+            match fs::read(&keyring) {
+                Ok(existing) if existing != key => {
+                    return Err(eyre!("{} already exists", keyring.display()));
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    fs::create_dir_all(APT_KEYRINGS_PATH)?;
+                    write_atomic(&keyring, key)?;
+                    new_keyring = Some(keyring);
+                }
+                Err(e) => return Err(eyre!("Failed to read {}: {e}", keyring.display())),
+            }
+        }
+        if let Err(e) = write_atomic(&path, source.render(format)) {
+            if let Some(keyring) = new_keyring {
+                let _ = fs::remove_file(keyring);
+            }
+            return Err(e);
+        }
+        self.load_repository_list()?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn docker() -> NewSource {
+        NewSource {
+            uri: "https://download.docker.com/linux/ubuntu".to_string(),
+            suite: "noble".to_string(),
+            components: "stable".to_string(),
+            architectures: "amd64".to_string(),
+            ..NewSource::default()
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(docker().validate(), vec![]);
+
+        let source = NewSource {
+            source_type: "rpm".to_string(),
+            uri: "download.docker.com".to_string(),
+            suite: "./".to_string(),
+            signed_by: "keyring.gpg".to_string(),
+            ..docker()
+        };
+        let fields: Vec<_> = source.validate().into_iter().map(|(f, _)| f).collect();
+        assert_eq!(
+            fields,
+            vec![
+                NewSourceField::Type,
+                NewSourceField::Uri,
+                NewSourceField::Components,
+                NewSourceField::SignedBy,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let source = docker();
+        assert_eq!(
+            source.file_name(SourceFormat::Deb822),
+            "download.docker.com-linux-ubuntu.sources"
+        );
+        assert_eq!(
+            source.render(SourceFormat::OneLine),
+            "deb [arch=amd64] https://download.docker.com/linux/ubuntu noble stable\n"
+        );
+        assert_eq!(
+            source.render(SourceFormat::Deb822),
+            "Types: deb\nURIs: https://download.docker.com/linux/ubuntu\nSuites: noble\nComponents: stable\nArchitectures: amd64\n"
+        );
+    }
+
+    #[test]
+    fn test_expand_ppa() {
+        let keyring = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/keyrings/example-release.asc"
+        );
+        let typed = NewSource {
+            uri: "ppa:deadsnakes/ppa".to_string(),
+            ..NewSource::default()
+        };
+        let source = typed.clone().expand_ppa(Some("noble"));
+        assert_eq!(
+            source.validate(),
+            vec![(
+                NewSourceField::SignedBy,
+                "required for a PPA, the path of its key downloaded from Launchpad".to_string()
+            )]
+        );
+
+        let source = NewSource {
+            signed_by: keyring.to_string(),
+            ..typed.clone()
+        }
+        .expand_ppa(Some("noble"));
+        assert_eq!(source.validate(), vec![]);
+        assert_eq!(
+            source.file_name(SourceFormat::OneLine),
+            "deadsnakes-ubuntu-ppa-noble.list"
+        );
+        assert_eq!(
+            source.keyring_destination(),
+            Some(PathBuf::from("/etc/apt/keyrings/deadsnakes-ubuntu-ppa.asc"))
+        );
+        assert_eq!(
+            source.render(SourceFormat::Deb822),
+            "Types: deb\nURIs: https://ppa.launchpadcontent.net/deadsnakes/ppa/ubuntu\n\
+             Suites: noble\nComponents: main\n\
+             Signed-By: /etc/apt/keyrings/deadsnakes-ubuntu-ppa.asc\n"
+        );
+
+        let invalid = NewSource {
+            uri: "ppa:Deadsnakes".to_string(),
+            ..typed
+        }
+        .expand_ppa(Some("noble"));
+        assert_eq!(
+            invalid.validate()[0],
+            (
+                NewSourceField::Uri,
+                "use ppa:owner/name, in lower case".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use color_eyre::{eyre::eyre, Result};
+
+use super::{AptSource, SourceType};
+
+/// Parses one line of a `.list` file.
+///
+/// Returns `Ok(None)` for blank lines and plain comments. A commented out entry such as
+/// `# deb http://archive.ubuntu.com/ubuntu noble main` is returned as a disabled source.
+pub fn parse_line(line: &str, line_number: usize) -> Result<Option<AptSource>> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    let (enabled, entry) = match trimmed.strip_prefix('#') {
+        Some(commented) => (false, commented.trim_start_matches('#').trim()),
+        None => (true, trimmed),
+    };
+
+    let Some((type_token, rest)) = split_token(entry) else {
+        return Ok(None);
+    };
+    let Some(source_type) = SourceType::parse(type_token) else {
+        if enabled {
+            return Err(eyre!(
+                "line {line_number}: unknown source type `{type_token}`"
+            ));
+        }
+        // Just a comment
+        return Ok(None);
+    };
+
+    match parse_entry(rest) {
+        Ok((options, uri, suite, components)) => Ok(Some(AptSource {
+            enabled,
+            source_type,
+            options,
+            uri,
+            suite,
+            components,
+            line_number,
+            original: line.to_string(),
+        })),
+        // A commented line that only looks like an entry is left alone
+        Err(_) if !enabled => Ok(None),
+        Err(e) => Err(eyre!("line {line_number}: {e}")),
+    }
+}
+
+type Entry = (Vec<(String, String)>, String, String, Vec<String>);
+
+fn parse_entry(rest: &str) -> Result<Entry> {
+    // An enabled entry ends at the first comment
+    let rest = rest
+        .split_once(" #")
+        .map_or(rest, |(entry, _)| entry)
+        .trim();
+
+    let mut options = Vec::new();
+    let rest = match rest.strip_prefix('[') {
+        Some(bracketed) => {
+            let Some((inner, rest)) = bracketed.split_once(']') else {
+                return Err(eyre!("unterminated option list"));
+            };
+            for option in inner.split_whitespace() {
+                let Some((key, value)) = option.split_once('=') else {
+                    return Err(eyre!("option `{option}` is not `key=value`"));
+                };
+                options.push((key.to_string(), value.to_string()));
+            }
+            rest.trim_start()
+        }
+        None => rest,
+    };
+
+    let (uri, rest) = split_uri(rest).ok_or_else(|| eyre!("missing URI"))?;
+    if !uri.contains(':') {
+        return Err(eyre!("`{uri}` is not a URI"));
+    }
+    let (suite, rest) = split_token(rest).ok_or_else(|| eyre!("missing suite"))?;
+    let components: Vec<String> = rest.split_whitespace().map(str::to_string).collect();
+    if suite.ends_with('/') && !components.is_empty() {
+        return Err(eyre!("exact path suite `{suite}` can't have components"));
+    }
+    if !suite.ends_with('/') && components.is_empty() {
+        return Err(eyre!("missing components"));
+    }
+    Ok((options, uri.to_string(), suite.to_string(), components))
+}
+
+fn split_token(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    if s.is_empty() {
+        return None;
+    }
+    Some(s.split_once(char::is_whitespace).unwrap_or((s, "")))
+}
+
+/// Like `split_token`, but keeps the spaces inside the brackets of `cdrom:[Disc Name]/` URIs.
+fn split_uri(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    let (token, _) = split_token(s)?;
+    if token.contains('[') && !token.contains(']') {
+        let end = s.find(']')?;
+        let end = s[end..]
+            .find(char::is_whitespace)
+            .map_or(s.len(), |offset| end + offset);
+        return Some((&s[..end], &s[end..]));
+    }
+    split_token(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_line_with_options() {
+        let source = parse_line(
+            "deb [arch=amd64 signed-by=/etc/apt/keyrings/docker.gpg] https://download.docker.com/linux/ubuntu noble stable",
+            3,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(source.enabled);
+        assert_eq!(source.source_type, SourceType::Deb);
+        assert_eq!(
+            source.options,
+            vec![
+                ("arch".to_string(), "amd64".to_string()),
+                (
+                    "signed-by".to_string(),
+                    "/etc/apt/keyrings/docker.gpg".to_string()
+                ),
+            ]
+        );
+        assert_eq!(source.uri, "https://download.docker.com/linux/ubuntu");
+        assert_eq!(source.suite, "noble");
+        assert_eq!(source.components, vec!["stable"]);
+        assert_eq!(source.line_number, 3);
+    }
+
+    #[test]
+    fn test_parse_line_commented() {
+        let source = parse_line(
+            "## deb-src http://archive.ubuntu.com/ubuntu noble main restricted # sources",
+            1,
+        )
+        .unwrap()
+        .unwrap();
+        assert!(!source.enabled);
+        assert_eq!(source.source_type, SourceType::DebSrc);
+        assert_eq!(source.components, vec!["main", "restricted"]);
+
+        assert_eq!(parse_line("# See sources.list(5)", 1).unwrap(), None);
+        assert_eq!(parse_line("# deb is the binary type", 1).unwrap(), None);
+        assert_eq!(parse_line("", 1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_line_cdrom_and_exact_path() {
+        let source = parse_line(
+            "# deb cdrom:[Debian GNU/Linux 12.0.0 _Bookworm_ - Official amd64]/ bookworm contrib main",
+            1,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            source.uri,
+            "cdrom:[Debian GNU/Linux 12.0.0 _Bookworm_ - Official amd64]/"
+        );
+        assert_eq!(source.suite, "bookworm");
+
+        let source = parse_line("deb [trusted=yes] file:/srv/repo ./", 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.suite, "./");
+        assert!(source.components.is_empty());
+    }
+
+    #[test]
+    fn test_parse_line_malformed() {
+        assert!(parse_line("deb http://deb.debian.org/debian", 1).is_err());
+        assert!(parse_line(
+            "deb [arch=amd64 http://deb.debian.org/debian bookworm main",
+            1
+        )
+        .is_err());
+        assert!(parse_line("rpm http://example.com/ stable main", 1).is_err());
+    }
+}
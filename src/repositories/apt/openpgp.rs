@@ -0,0 +1,602 @@
+//! Just enough of OpenPGP (RFC 4880 and RFC 9580) to tell what is in a keyring: the keys,
+//! their user IDs and when they expire. Signatures are read but not verified.
+
+use base64::Engine;
+use color_eyre::{eyre::eyre, Result};
+use sha2::Digest;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TAG_SIGNATURE: u8 = 2;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_TRUST: u8 = 12;
+const TAG_USER_ID: u8 = 13;
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+
+const SUBPACKET_CREATION_TIME: u8 = 2;
+const SUBPACKET_KEY_EXPIRATION_TIME: u8 = 9;
+const SUBPACKET_ISSUER: u8 = 16;
+const SUBPACKET_ISSUER_FINGERPRINT: u8 = 33;
+
+const SIGNATURE_SUBKEY_BINDING: u8 = 0x18;
+const SIGNATURE_DIRECT_KEY: u8 = 0x1f;
+const SIGNATURE_KEY_REVOCATION: u8 = 0x20;
+
+/// Keys expiring within this many seconds are flagged.
+pub const EXPIRY_WARNING_SECONDS: u64 = 90 * 24 * 60 * 60;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Subkey {
+    pub fingerprint: String,
+    pub created: u64,
+    pub expires: Option<u64>,
+}
+
+/// A public key with everything attached to it in the keyring.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    pub version: u8,
+    /// Upper case hex, without spaces.
+    pub fingerprint: String,
+    /// Seconds since the epoch, like `expires`.
+    pub created: u64,
+    pub expires: Option<u64>,
+    pub revoked: bool,
+    pub user_ids: Vec<String>,
+    pub subkeys: Vec<Subkey>,
+}
+
+/// Whether a key can still be used, relative to a point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyExpiry {
+    Revoked,
+    Expired(u64),
+    Soon(u64),
+    Valid(u64),
+    Never,
+}
+
+impl PublicKey {
+    pub fn name(&self) -> &str {
+        self.user_ids
+            .first()
+            .map(String::as_str)
+            .unwrap_or("(no user ID)")
+    }
+
+    /// The first user ID without its email address, e.g. `Docker Release (CE deb)`.
+    pub fn name_without_email(&self) -> &str {
+        let name = self.name();
+        match name.rfind(" <") {
+            Some(start) if name.ends_with('>') => &name[..start],
+            _ => name,
+        }
+    }
+
+    /// Whether the issuer of a signature, a key ID or a fingerprint, is this key or one of
+    /// its subkeys.
+    pub fn matches_issuer(&self, issuer: &str) -> bool {
+        std::iter::once(&self.fingerprint)
+            .chain(self.subkeys.iter().map(|subkey| &subkey.fingerprint))
+            .any(|fingerprint| {
+                fingerprint.eq_ignore_ascii_case(issuer)
+                    || (issuer.len() == 16
+                        && (fingerprint.ends_with(issuer) || fingerprint.starts_with(issuer)))
+            })
+    }
+
+    /// When the key can no longer sign: the expiry of the primary key when it has no subkeys,
+    /// otherwise the one of the subkey that stays valid longest, which the primary key's
+    /// expiry still bounds. `None` for never.
+    pub fn effective_expires(&self) -> Option<u64> {
+        let Some(latest) = self
+            .subkeys
+            .iter()
+            .map(|subkey| subkey.expires)
+            .max_by_key(|expires| expires.unwrap_or(u64::MAX))
+        else {
+            return self.expires;
+        };
+        match (latest, self.expires) {
+            (Some(latest), Some(expires)) => Some(latest.min(expires)),
+            (latest, expires) => latest.or(expires),
+        }
+    }
+
+    pub fn expiry(&self, now: u64) -> KeyExpiry {
+        match self.effective_expires() {
+            _ if self.revoked => KeyExpiry::Revoked,
+            Some(expires) if expires <= now => KeyExpiry::Expired(expires),
+            Some(expires) if expires - now <= EXPIRY_WARNING_SECONDS => KeyExpiry::Soon(expires),
+            Some(expires) => KeyExpiry::Valid(expires),
+            None => KeyExpiry::Never,
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// `YYYY-MM-DD` in UTC for seconds since the epoch.
+pub fn format_date(timestamp: u64) -> String {
+    // Howard Hinnant's days to civil date conversion
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+struct Packet<'a> {
+    tag: u8,
+    body: &'a [u8],
+    /// The whole packet, header included.
+    raw: &'a [u8],
+}
+
+fn be_u32(bytes: &[u8]) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?))
+}
+
+/// Splits binary OpenPGP data into packets.
+fn packets(mut data: &[u8]) -> Result<Vec<Packet<'_>>> {
+    let mut packets = Vec::new();
+    while let Some((&header, rest)) = data.split_first() {
+        if header & 0x80 == 0 {
+            return Err(eyre!("not OpenPGP data"));
+        }
+        let (tag, length, rest) = if header & 0x40 != 0 {
+            let tag = header & 0x3f;
+            match rest {
+                [first, rest @ ..] if *first < 192 => (tag, *first as usize, rest),
+                [first, second, rest @ ..] if *first < 224 => (
+                    tag,
+                    ((*first as usize - 192) << 8) + *second as usize + 192,
+                    rest,
+                ),
+                [255, rest @ ..] => (
+                    tag,
+                    be_u32(rest).ok_or_else(|| eyre!("truncated packet"))? as usize,
+                    &rest[4..],
+                ),
+                [_, ..] => return Err(eyre!("partial body lengths aren't used for keys")),
+                [] => return Err(eyre!("truncated packet")),
+            }
+        } else {
+            let tag = (header >> 2) & 0x0f;
+            let (length, rest) = match header & 0x03 {
+                0 => rest
+                    .split_first()
+                    .map(|(length, rest)| (*length as usize, rest)),
+                1 => rest.get(..2).map(|length| {
+                    (
+                        u16::from_be_bytes([length[0], length[1]]) as usize,
+                        &rest[2..],
+                    )
+                }),
+                2 => be_u32(rest).map(|length| (length as usize, &rest[4..])),
+                _ => Some((rest.len(), rest)),
+            }
+            .ok_or_else(|| eyre!("truncated packet"))?;
+            (tag, length, rest)
+        };
+        if rest.len() < length {
+            return Err(eyre!("truncated packet"));
+        }
+        let header_length = data.len() - rest.len();
+        packets.push(Packet {
+            tag,
+            body: &rest[..length],
+            raw: &data[..header_length + length],
+        });
+        data = &rest[length..];
+    }
+    Ok(packets)
+}
+
+/// Version, creation time and fingerprint of a public key or subkey packet.
+fn parse_key(body: &[u8]) -> Result<(u8, u64, String)> {
+    let version = *body.first().ok_or_else(|| eyre!("empty key packet"))?;
+    let created = be_u32(&body[1..]).ok_or_else(|| eyre!("truncated key packet"))? as u64;
+    let fingerprint: Vec<u8> = match version {
+        4 => {
+            let mut hasher = sha1_smol::Sha1::new();
+            hasher.update(&[0x99]);
+            hasher.update(&(body.len() as u16).to_be_bytes());
+            hasher.update(body);
+            hasher.digest().bytes().to_vec()
+        }
+        5 | 6 => {
+            let mut hasher = sha2::Sha256::new();
+            hasher.update([if version == 5 { 0x9a } else { 0x9b }]);
+            hasher.update((body.len() as u32).to_be_bytes());
+            hasher.update(body);
+            hasher.finalize().to_vec()
+        }
+        _ => return Err(eyre!("version {version} keys aren't supported")),
+    };
+    let fingerprint = fingerprint.iter().map(|b| format!("{b:02X}")).collect();
+    Ok((version, created, fingerprint))
+}
+
+/// The parts of a signature packet needed to find expiry dates.
+#[derive(Debug, Default)]
+struct Signature {
+    signature_type: u8,
+    created: u64,
+    key_expiration: Option<u64>,
+    /// Issuer key ID or fingerprint, upper case hex.
+    issuer: Option<String>,
+}
+
+fn parse_signature(body: &[u8]) -> Option<Signature> {
+    let (&version, rest) = body.split_first()?;
+    let (signature_type, rest) = rest.split_first()?;
+    // Public key and hash algorithm
+    let rest = rest.get(2..)?;
+    let mut signature = Signature {
+        signature_type: *signature_type,
+        ..Signature::default()
+    };
+    let (hashed, rest) = match version {
+        4 => {
+            let length = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+            let rest = rest.get(2..)?;
+            (rest.get(..length)?, rest.get(length..)?)
+        }
+        6 => {
+            let length = be_u32(rest)? as usize;
+            let rest = rest.get(4..)?;
+            (rest.get(..length)?, rest.get(length..)?)
+        }
+        // v3 signatures carry no expiry
+        _ => return None,
+    };
+    let unhashed = match version {
+        4 => {
+            let length = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+            rest.get(2..2 + length)?
+        }
+        _ => {
+            let length = be_u32(rest)? as usize;
+            rest.get(4..4 + length)?
+        }
+    };
+
+    for (area, hashed) in [(hashed, true), (unhashed, false)] {
+        let mut area = area;
+        while let Some((&first, rest)) = area.split_first() {
+            let (length, rest) = match first {
+                0..=191 => (first as usize, rest),
+                192..=254 => (
+                    ((first as usize - 192) << 8) + *rest.first()? as usize + 192,
+                    rest.get(1..)?,
+                ),
+                255 => (be_u32(rest)? as usize, rest.get(4..)?),
+            };
+            let subpacket = rest.get(..length)?;
+            area = rest.get(length..)?;
+            let Some((&subpacket_type, data)) = subpacket.split_first() else {
+                continue;
+            };
+            let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{b:02X}")).collect::<String>();
+            // Only the issuer may come from the unhashed area, which anyone can change
+            match subpacket_type & 0x7f {
+                SUBPACKET_CREATION_TIME if hashed => signature.created = be_u32(data)? as u64,
+                SUBPACKET_KEY_EXPIRATION_TIME if hashed => {
+                    signature.key_expiration = Some(be_u32(data)? as u64)
+                }
+                SUBPACKET_ISSUER if signature.issuer.is_none() => {
+                    signature.issuer = Some(hex(data))
+                }
+                SUBPACKET_ISSUER_FINGERPRINT => signature.issuer = data.get(1..).map(hex),
+                _ => {}
+            }
+        }
+    }
+    Some(signature)
+}
+
+/// Applies the newest self-signature's expiry to a key created at `created`. A direct key
+/// signature only counts when it sets an expiry: they are also used for other things, such
+/// as naming a revocation key, and those say nothing about the expiry.
+fn expiry_from(signatures: &[Signature], created: u64) -> Option<u64> {
+    signatures
+        .iter()
+        .filter(|signature| {
+            signature.signature_type != SIGNATURE_DIRECT_KEY || signature.key_expiration.is_some()
+        })
+        .max_by_key(|signature| signature.created)
+        .and_then(|signature| signature.key_expiration)
+        .filter(|seconds| *seconds > 0)
+        .map(|seconds| created + seconds)
+}
+
+/// The binary form of `data`, which may be ASCII armored with `label`, such as
+/// `PUBLIC KEY BLOCK`.
+fn binary_blocks(data: &[u8], label: &str) -> Result<Vec<Vec<u8>>> {
+    if data.get(8..12) == Some(b"KBXf") {
+        return Err(eyre!(
+            "GnuPG keybox files aren't supported, export the keys with gpg --export"
+        ));
+    }
+    if data.trim_ascii_start().starts_with(b"-----BEGIN PGP") {
+        return dearmor(&String::from_utf8_lossy(data), label);
+    }
+    Ok(vec![data.to_vec()])
+}
+
+/// Reads the keys of binary (`.gpg`) or ASCII armored (`.asc`) OpenPGP data.
+pub fn parse_keyring(data: &[u8]) -> Result<Vec<PublicKey>> {
+    let mut keys = Vec::new();
+    for block in binary_blocks(data, "PUBLIC KEY BLOCK")? {
+        keys.extend(parse_binary(&block)?);
+    }
+    Ok(keys)
+}
+
+pub fn read_keyring(path: &Path) -> Result<Vec<PublicKey>> {
+    let data = fs::read(path).map_err(|e| eyre!("Unable to read {}: {e}", path.display()))?;
+    parse_keyring(&data)
+}
+
+/// Splits a keyring into one binary keyring per primary key, leaving out the trust packets
+/// GnuPG adds. Each part can be written out as a `.gpg` file of its own.
+pub fn split_keyring(data: &[u8]) -> Result<Vec<(PublicKey, Vec<u8>)>> {
+    let mut parts: Vec<Vec<u8>> = Vec::new();
+    for block in binary_blocks(data, "PUBLIC KEY BLOCK")? {
+        for packet in packets(&block)? {
+            match (packet.tag, parts.last_mut()) {
+                (TAG_TRUST, _) => {}
+                (TAG_PUBLIC_KEY, _) | (_, None) => parts.push(packet.raw.to_vec()),
+                (_, Some(part)) => part.extend_from_slice(packet.raw),
+            }
+        }
+    }
+    parts
+        .into_iter()
+        .map(|part| {
+            let key = parse_binary(&part)?
+                .pop()
+                .ok_or_else(|| eyre!("packets before the first key"))?;
+            Ok((key, part))
+        })
+        .collect()
+}
+
+/// The key IDs or fingerprints that made the signatures in `data`: a detached signature
+/// such as `Release.gpg`, or a clear signed message such as `InRelease`.
+pub fn signature_issuers(data: &[u8]) -> Result<Vec<String>> {
+    let mut issuers = Vec::new();
+    for block in binary_blocks(data, "SIGNATURE")? {
+        for packet in packets(&block)? {
+            if packet.tag != TAG_SIGNATURE {
+                continue;
+            }
+            if let Some(issuer) = parse_signature(packet.body).and_then(|s| s.issuer) {
+                issuers.push(issuer);
+            }
+        }
+    }
+    Ok(issuers)
+}
+
+/// Decodes every `-----BEGIN PGP <label>-----` block in `text`.
+fn dearmor(text: &str, label: &str) -> Result<Vec<Vec<u8>>> {
+    let begin = format!("-----BEGIN PGP {label}-----");
+    let mut blocks = Vec::new();
+    let mut lines = text.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if line != begin {
+            continue;
+        }
+        // Armor headers such as `Comment:` end at the first empty line
+        let mut body = String::new();
+        let mut in_headers = true;
+        for line in lines.by_ref() {
+            if line.starts_with("-----END PGP") {
+                break;
+            }
+            if in_headers {
+                if line.is_empty() {
+                    in_headers = false;
+                } else if !line.contains(':') {
+                    in_headers = false;
+                    body.push_str(line);
+                }
+                continue;
+            }
+            // The optional CRC24 checksum
+            if !line.starts_with('=') {
+                body.push_str(line);
+            }
+        }
+        blocks.push(
+            base64::engine::general_purpose::STANDARD
+                .decode(body)
+                .map_err(|e| eyre!("invalid armor: {e}"))?,
+        );
+    }
+    if blocks.is_empty() {
+        return Err(eyre!("no {} found", label.to_lowercase()));
+    }
+    Ok(blocks)
+}
+
+fn parse_binary(data: &[u8]) -> Result<Vec<PublicKey>> {
+    let mut keys: Vec<PublicKey> = Vec::new();
+    // Self-signatures of the current key and of its current subkey
+    let mut key_signatures = Vec::new();
+    let mut subkey_signatures = Vec::new();
+    let mut in_subkey = false;
+
+    let finish = |keys: &mut Vec<PublicKey>,
+                  key_signatures: &[Signature],
+                  subkey_signatures: &[Signature]| {
+        if let Some(key) = keys.last_mut() {
+            if let Some(subkey) = key.subkeys.last_mut() {
+                if subkey.expires.is_none() {
+                    subkey.expires = expiry_from(subkey_signatures, subkey.created);
+                }
+            }
+            key.expires = expiry_from(key_signatures, key.created);
+        }
+    };
+
+    for packet in packets(data)? {
+        match packet.tag {
+            TAG_PUBLIC_KEY => {
+                finish(&mut keys, &key_signatures, &subkey_signatures);
+                key_signatures.clear();
+                subkey_signatures.clear();
+                in_subkey = false;
+                let (version, created, fingerprint) = parse_key(packet.body)?;
+                keys.push(PublicKey {
+                    version,
+                    fingerprint,
+                    created,
+                    ..PublicKey::default()
+                });
+            }
+            TAG_PUBLIC_SUBKEY => {
+                let Some(key) = keys.last_mut() else {
+                    return Err(eyre!("subkey without a primary key"));
+                };
+                if let Some(subkey) = key.subkeys.last_mut() {
+                    subkey.expires = expiry_from(&subkey_signatures, subkey.created);
+                }
+                subkey_signatures.clear();
+                in_subkey = true;
+                let (_, created, fingerprint) = parse_key(packet.body)?;
+                key.subkeys.push(Subkey {
+                    fingerprint,
+                    created,
+                    expires: None,
+                });
+            }
+            TAG_USER_ID => {
+                in_subkey = false;
+                if let Some(key) = keys.last_mut() {
+                    key.user_ids
+                        .push(String::from_utf8_lossy(packet.body).into_owned());
+                }
+            }
+            TAG_SIGNATURE => {
+                let (Some(key), Some(signature)) = (keys.last_mut(), parse_signature(packet.body))
+                else {
+                    continue;
+                };
+                let by_key = signature.issuer.as_ref().is_none_or(|issuer| {
+                    key.fingerprint.ends_with(issuer.as_str())
+                        || key.fingerprint.starts_with(issuer.as_str())
+                });
+                if !by_key {
+                    // Certifications by other keys say nothing about this key's expiry
+                    continue;
+                }
+                match signature.signature_type {
+                    SIGNATURE_KEY_REVOCATION => key.revoked = true,
+                    SIGNATURE_SUBKEY_BINDING if in_subkey => subkey_signatures.push(signature),
+                    0x10..=0x13 | SIGNATURE_DIRECT_KEY if !in_subkey => {
+                        key_signatures.push(signature)
+                    }
+                    _ => {}
+                }
+            }
+            // Trust packets, user attributes and the like
+            _ => {}
+        }
+    }
+    finish(&mut keys, &key_signatures, &subkey_signatures);
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keyrings");
+
+    fn fixture(name: &str) -> Vec<PublicKey> {
+        read_keyring(&Path::new(FIXTURES).join(name)).unwrap()
+    }
+
+    #[test]
+    fn test_armored_key_with_subkey() {
+        let keys = fixture("example-release.asc");
+        assert_eq!(keys.len(), 1);
+        let key = &keys[0];
+        assert_eq!(key.fingerprint, "37A87F21F5F115D4AD1132F80410C264E1AC33F7");
+        assert_eq!(
+            key.user_ids,
+            vec![
+                "Example Release (CE deb) <release@example.com>",
+                "Example Archive <archive@example.com>"
+            ]
+        );
+        assert_eq!(format_date(key.created), "2024-01-02");
+        assert_eq!(key.expires, Some(1798804800));
+        assert_eq!(format_date(1798804800), "2027-01-01");
+        assert_eq!(
+            key.subkeys,
+            vec![Subkey {
+                fingerprint: "8F92F018BD39B18881E5AFE3DB064A0E70222FE0".to_string(),
+                created: 1704164645,
+                expires: Some(1767268800),
+            }]
+        );
+        // The subkey expired before the primary key does, so nothing can sign any more
+        assert_eq!(key.effective_expires(), Some(1767268800));
+        assert_eq!(key.expiry(1767268800 - 86_400), KeyExpiry::Soon(1767268800));
+        assert_eq!(key.expiry(now()), KeyExpiry::Expired(1767268800));
+    }
+
+    #[test]
+    fn test_binary_expired_key() {
+        let keys = fixture("expired-vendor.gpg");
+        assert_eq!(keys.len(), 1);
+        assert_eq!(
+            keys[0].fingerprint,
+            "F879F810ED4ECB83423926DC8965BABEFCE37B06"
+        );
+        assert_eq!(keys[0].name(), "Expired Vendor <keys@expired.example>");
+        assert_eq!(keys[0].name_without_email(), "Expired Vendor");
+        assert_eq!(keys[0].created, 1577836800);
+        assert_eq!(keys[0].expiry(now()), KeyExpiry::Expired(1609372800));
+    }
+
+    #[test]
+    fn test_debian_archive_key() {
+        let keys = fixture("debian-archive-bookworm-stable.asc");
+        assert_eq!(
+            keys[0].fingerprint,
+            "4D64FEC119C2029067D6E791F8D2585B8783D481"
+        );
+        assert_eq!(
+            keys[0].name(),
+            "Debian Stable Release Key (12/bookworm) <debian-release@lists.debian.org>"
+        );
+        assert_eq!(keys[0].expires, Some(1926780243));
+    }
+
+    #[test]
+    fn test_not_a_keyring() {
+        assert!(parse_keyring(b"deb http://deb.debian.org/debian bookworm main").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs::{self, Permissions};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use super::{
+    auth::{AUTH_CONF_D_PATH, AUTH_CONF_PATH},
+    keyrings::{APT_KEYRINGS_PATH, APT_TRUSTED_GPG_D_PATH, LEGACY_TRUSTED_GPG_PATH},
+    AptRepositories, APT_CONF_D_PATH, APT_CONF_PATH, APT_PREFERENCES_D_PATH, APT_PREFERENCES_PATH,
+    APT_SOURCES_LIST_D_PATH, APT_SOURCES_LIST_PATH,
+};
+
+/// Links in the configuration that point out of this directory are reported, whoever can
+/// write where they point can change what apt reads.
+const ETC_PATH: &str = "/etc";
+const APT_ETC_PATH: &str = "/etc/apt";
+
+/// Whether yacufu runs as root, and so can change modes and owners.
+pub fn is_privileged() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// How much a finding lets someone other than root change what apt installs, worst first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Risk {
+    /// Every user can change it.
+    Critical,
+    /// One user other than root can change it.
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Risk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Critical => write!(f, "critical"),
+            Self::High => write!(f, "high"),
+            Self::Medium => write!(f, "medium"),
+            Self::Low => write!(f, "low"),
+        }
+    }
+}
+
+/// The change that takes care of a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionFix {
+    /// Clears these write bits of the mode.
+    RemoveWrite(u32),
+    /// Gives the file to root, keeping its group.
+    OwnByRoot,
+}
+
+impl fmt::Display for PermissionFix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RemoveWrite(bits) => {
+                let who = match (bits & 0o020 != 0, bits & 0o002 != 0) {
+                    (true, true) => "go",
+                    (true, false) => "g",
+                    _ => "o",
+                };
+                write!(f, "chmod {who}-w")
+            }
+            Self::OwnByRoot => write!(f, "chown root"),
+        }
+    }
+}
+
+/// A file or directory apt reads that others than root can change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionFinding {
+    pub path: PathBuf,
+    pub risk: Risk,
+    /// Short stable name of the check, e.g. `world-writable`.
+    pub code: &'static str,
+    pub message: String,
+    /// `None` when there is no change that is right in every case, as for links.
+    pub fix: Option<PermissionFix>,
+}
+
+/// `path`, followed by its entries when it is a directory.
+fn with_entries(path: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(path) {
+        Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort();
+    entries.insert(0, path.to_path_buf());
+    entries
+}
+
+/// Checks the owner and mode of `paths`, following links. Links under `etc` that point out of
+/// it are reported too, and what is found through them gets no fix.
+fn audit_paths(paths: &[PathBuf], etc: &Path) -> Vec<PermissionFinding> {
+    let mut findings = Vec::new();
+    let mut report = |path: &Path, risk, code, message, fix| {
+        findings.push(PermissionFinding {
+            path: path.to_path_buf(),
+            risk,
+            code,
+            message,
+            fix,
+        })
+    };
+    let mut seen = BTreeSet::new();
+    for path in paths {
+        if !seen.insert(path) {
+            continue;
+        }
+        let Ok(link_metadata) = fs::symlink_metadata(path) else {
+            continue;
+        };
+        // chmod and chown follow links, so a path that leads out of `etc` is left for the
+        // user to fix, it may be a file of theirs
+        let fixable =
+            fs::canonicalize(path).is_ok_and(|target| target == *path || target.starts_with(etc));
+        if link_metadata.file_type().is_symlink() {
+            match fs::canonicalize(path) {
+                Ok(target) if path.starts_with(etc) && !target.starts_with(etc) => {
+                    let message =
+                        format!("links to {}, outside {}", target.display(), etc.display());
+                    report(path, Risk::Medium, "symlink-outside-etc", message, None);
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    let message = "links to nothing, apt can't read it".to_string();
+                    report(path, Risk::Low, "broken-symlink", message, None);
+                    continue;
+                }
+            }
+        }
+        let Ok(metadata) = fs::metadata(path) else {
+            continue;
+        };
+        let mode = metadata.mode() & 0o7777;
+        let kind = if metadata.is_dir() {
+            "directory"
+        } else {
+            "file"
+        };
+        if mode & 0o002 != 0 {
+            let message = format!("every user can write to this {kind}, mode {mode:04o}");
+            let fix = fixable.then_some(PermissionFix::RemoveWrite(0o002));
+            report(path, Risk::Critical, "world-writable", message, fix);
+        }
+        if metadata.uid() != 0 {
+            let message = format!("owned by uid {}, who can change it", metadata.uid());
+            let fix = fixable.then_some(PermissionFix::OwnByRoot);
+            report(path, Risk::High, "not-owned-by-root", message, fix);
+        }
+        if mode & 0o020 != 0 {
+            // The root group has no members but root on most systems
+            let risk = if metadata.gid() == 0 {
+                Risk::Low
+            } else {
+                Risk::Medium
+            };
+            let message = format!(
+                "group {} can write to this {kind}, mode {mode:04o}",
+                metadata.gid()
+            );
+            let fix = fixable.then_some(PermissionFix::RemoveWrite(0o020));
+            report(path, risk, "group-writable", message, fix);
+        }
+    }
+    findings.sort_by(|a, b| (a.risk, &a.path).cmp(&(b.risk, &b.path)));
+    findings
+}
+
+/// Applies the fixes of `findings` and returns how many there were. Stops at the first one
+/// that fails.
+pub fn fix_permissions(findings: &[PermissionFinding]) -> Result<usize> {
+    let mut count = 0;
+    for finding in findings {
+        let path = &finding.path;
+        let result = match finding.fix {
+            Some(PermissionFix::RemoveWrite(bits)) => fs::metadata(path).and_then(|metadata| {
+                let mode = metadata.mode() & 0o7777 & !bits;
+                fs::set_permissions(path, Permissions::from_mode(mode))
+            }),
+            Some(PermissionFix::OwnByRoot) => std::os::unix::fs::chown(path, Some(0), None),
+            None => continue,
+        };
+        result.map_err(|e| eyre!("Failed to fix {}: {e}", path.display()))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+impl AptRepositories {
+    /// Checks everything apt reads its sources, keys and settings from: the source files,
+    /// the keyrings they are signed with, `apt.conf.d`, the preferences and the login files.
+    pub fn audit_permissions(&self) -> Vec<PermissionFinding> {
+        let mut paths = vec![PathBuf::from(APT_ETC_PATH)];
+        for file in [
+            APT_SOURCES_LIST_PATH,
+            LEGACY_TRUSTED_GPG_PATH,
+            APT_CONF_PATH,
+            APT_PREFERENCES_PATH,
+            AUTH_CONF_PATH,
+        ] {
+            paths.push(PathBuf::from(file));
+        }
+        for dir in [
+            APT_SOURCES_LIST_D_PATH,
+            APT_TRUSTED_GPG_D_PATH,
+            APT_KEYRINGS_PATH,
+            APT_CONF_D_PATH,
+            APT_PREFERENCES_D_PATH,
+            AUTH_CONF_D_PATH,
+        ] {
+            paths.extend(with_entries(Path::new(dir)));
+        }
+        paths.extend(
+            self.sources()
+                .filter_map(|(_, source)| source.signed_by_path())
+                .map(PathBuf::from),
+        );
+        audit_paths(&paths, Path::new(ETC_PATH))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+    use crate::repositories::apt::test_support::TempDir;
+
+    #[test]
+    fn test_audit_paths() {
+        let dir = TempDir::new("permissions");
+        let etc = dir.join("etc");
+        let sources_dir = etc.join("sources.list.d");
+        fs::create_dir_all(&sources_dir).unwrap();
+        let outside = dir.join("outside.list");
+        fs::write(&outside, "deb https://outside.example/apt stable main\n").unwrap();
+        fs::set_permissions(&outside, Permissions::from_mode(0o666)).unwrap();
+        for (name, mode) in [("fine.list", 0o644), ("open.list", 0o666)] {
+            let path = sources_dir.join(name);
+            fs::write(&path, "deb https://vendor.example/apt stable main\n").unwrap();
+            fs::set_permissions(&path, Permissions::from_mode(mode)).unwrap();
+        }
+        fs::set_permissions(&sources_dir, Permissions::from_mode(0o755)).unwrap();
+        symlink(&outside, sources_dir.join("linked.list")).unwrap();
+        symlink(dir.join("missing.list"), sources_dir.join("broken.list")).unwrap();
+
+        let findings = audit_paths(&with_entries(&sources_dir), &etc);
+        // Running the tests as root or not changes nothing about these
+        let found: Vec<(&str, Risk, &str)> = findings
+            .iter()
+            .filter(|finding| finding.code != "not-owned-by-root")
+            .map(|finding| {
+                let name = finding.path.file_name().unwrap().to_str().unwrap();
+                (name, finding.risk, finding.code)
+            })
+            .collect();
+        let group_risk = if fs::metadata(sources_dir.join("open.list")).unwrap().gid() == 0 {
+            Risk::Low
+        } else {
+            Risk::Medium
+        };
+        let mut expected = vec![
+            ("open.list", Risk::Critical, "world-writable"),
+            ("linked.list", Risk::Critical, "world-writable"),
+            ("linked.list", Risk::Medium, "symlink-outside-etc"),
+            ("broken.list", Risk::Low, "broken-symlink"),
+            ("open.list", group_risk, "group-writable"),
+            ("linked.list", group_risk, "group-writable"),
+        ];
+        expected.sort_by_key(|(name, risk, _)| (*risk, sources_dir.join(name)));
+        assert_eq!(found, expected);
+
+        let fixes: Vec<PermissionFinding> = findings
+            .into_iter()
+            .filter(|finding| finding.code.ends_with("-writable"))
+            .collect();
+        assert_eq!(fix_permissions(&fixes).unwrap(), 2);
+        let mode = fs::metadata(sources_dir.join("open.list")).unwrap().mode() & 0o7777;
+        assert_eq!(mode, 0o644);
+        // The file outside `etc` the link leads to is left alone
+        assert_eq!(fs::metadata(&outside).unwrap().mode() & 0o7777, 0o666);
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use super::{auth::strip_credentials, AptRepositories, SourceRef};
+
+/// Where Launchpad serves PPAs from.
+pub const PPA_HOST: &str = "ppa.launchpadcontent.net";
+/// The host PPAs were served from before 2022, still found in older source files.
+const LEGACY_PPA_HOST: &str = "ppa.launchpad.net";
+
+/// A Launchpad PPA, written `ppa:owner/name`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ppa {
+    pub owner: String,
+    pub name: String,
+}
+
+/// Launchpad names: lower case letters, digits and `+-.`, starting with a letter or digit.
+fn is_launchpad_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "+-.".contains(c))
+}
+
+impl Ppa {
+    /// Whether `value` is meant as a PPA shorthand rather than a URI.
+    pub fn is_shorthand(value: &str) -> bool {
+        value.trim().starts_with("ppa:")
+    }
+
+    /// Parses `ppa:owner/name`. Like add-apt-repository, `ppa:owner` stands for the PPA
+    /// named `ppa`.
+    pub fn parse(value: &str) -> Result<Self, &'static str> {
+        let Some(rest) = value.trim().strip_prefix("ppa:") else {
+            return Err("must start with ppa:");
+        };
+        let (owner, name) = rest.split_once('/').unwrap_or((rest, "ppa"));
+        if !is_launchpad_name(owner) || !is_launchpad_name(name) {
+            return Err("use ppa:owner/name, in lower case");
+        }
+        Ok(Self {
+            owner: owner.to_string(),
+            name: name.to_string(),
+        })
+    }
+
+    /// The PPA a source URI points at, on either Launchpad host.
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let uri = strip_credentials(uri);
+        let rest = uri.split_once("://")?.1;
+        let mut parts = rest.trim_end_matches('/').split('/');
+        let host = parts.next()?;
+        if !host.eq_ignore_ascii_case(PPA_HOST) && !host.eq_ignore_ascii_case(LEGACY_PPA_HOST) {
+            return None;
+        }
+        let (owner, name, distro) = (parts.next()?, parts.next()?, parts.next()?);
+        (distro == "ubuntu" && parts.next().is_none()).then(|| Self {
+            owner: owner.to_string(),
+            name: name.to_string(),
+        })
+    }
+
+    pub fn uri(&self) -> String {
+        format!("https://{PPA_HOST}/{}/{}/ubuntu", self.owner, self.name)
+    }
+
+    /// The name add-apt-repository gives the source file, without its extension, e.g.
+    /// `deadsnakes-ubuntu-ppa-noble`. Dots become `_` as they do in its names.
+    pub fn file_stem(&self, codename: &str) -> String {
+        format!("{}-ubuntu-{}-{codename}", self.owner, self.name).replace('.', "_")
+    }
+
+    /// The name add-apt-repository gives the PPA's keyring, without its extension.
+    pub fn keyring_stem(&self) -> String {
+        format!("{}-ubuntu-{}", self.owner, self.name).replace('.', "_")
+    }
+}
+
+impl fmt::Display for Ppa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ppa:{}/{}", self.owner, self.name)
+    }
+}
+
+impl AptRepositories {
+    /// The loaded sources that point at a PPA, `deb-src` and disabled entries included.
+    pub fn ppa_sources(&self) -> Vec<(SourceRef, Ppa)> {
+        self.source_refs()
+            .filter_map(|(source_ref, _, source)| {
+                Ppa::from_uri(&source.uri).map(|ppa| (source_ref, ppa))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_ppa() {
+        let ppa = Ppa::parse("ppa:deadsnakes/ppa").unwrap();
+        assert_eq!(
+            ppa.uri(),
+            "https://ppa.launchpadcontent.net/deadsnakes/ppa/ubuntu"
+        );
+        assert_eq!(ppa.file_stem("noble"), "deadsnakes-ubuntu-ppa-noble");
+        assert_eq!(Ppa::from_uri(&ppa.uri()), Some(ppa.clone()));
+        assert_eq!(
+            Ppa::from_uri("http://ppa.launchpad.net/deadsnakes/ppa/ubuntu/"),
+            Some(ppa)
+        );
+        assert_eq!(
+            Ppa::parse("ppa:git-core").map(|ppa| ppa.to_string()),
+            Ok("ppa:git-core/ppa".to_string())
+        );
+        assert_eq!(
+            Ppa::parse("ppa:kelleyk/emacs.29")
+                .unwrap()
+                .file_stem("jammy"),
+            "kelleyk-ubuntu-emacs_29-jammy"
+        );
+        assert!(Ppa::parse("ppa:Owner/name").is_err());
+        assert!(Ppa::parse("ppa:/name").is_err());
+        assert_eq!(
+            Ppa::from_uri("https://ppa.launchpadcontent.net/a/b/debian"),
+            None
+        );
+        assert_eq!(Ppa::from_uri("https://deb.debian.org/debian"), None);
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+
+use tracing::warn;
+
+use super::{auth::strip_credentials, AptRepositories, SourceRef};
+
+const OS_RELEASE_PATHS: [&str; 2] = ["/etc/os-release", "/usr/lib/os-release"];
+
+/// Debian releases, oldest first.
+const DEBIAN_CODENAMES: [&str; 18] = [
+    "buzz", "rex", "bo", "hamm", "slink", "potato", "woody", "sarge", "etch", "lenny", "squeeze",
+    "wheezy", "jessie", "stretch", "buster", "bullseye", "bookworm", "trixie",
+];
+
+/// Debian releases that are announced but not released yet.
+const DEBIAN_FUTURE_CODENAMES: [&str; 2] = ["forky", "duke"];
+
+/// Debian suites that follow unstable rather than a release.
+const DEBIAN_DEVELOPMENT_SUITES: [&str; 4] = ["unstable", "sid", "experimental", "rc-buggy"];
+
+/// Ubuntu releases, oldest first.
+const UBUNTU_CODENAMES: [&str; 44] = [
+    "warty", "hoary", "breezy", "dapper", "edgy", "feisty", "gutsy", "hardy", "intrepid", "jaunty",
+    "karmic", "lucid", "maverick", "natty", "oneiric", "precise", "quantal", "raring", "saucy",
+    "trusty", "utopic", "vivid", "wily", "xenial", "yakkety", "zesty", "artful", "bionic",
+    "cosmic", "disco", "eoan", "focal", "groovy", "hirsute", "impish", "jammy", "kinetic", "lunar",
+    "mantic", "noble", "oracular", "plucky", "questing", "resolute",
+];
+
+/// Pocket suffixes that belong to the release they are appended to.
+pub(crate) const POCKETS: [&str; 6] = [
+    "-proposed-updates",
+    "-backports-sloppy",
+    "-backports",
+    "-security",
+    "-updates",
+    "-proposed",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Distro {
+    Debian,
+    Ubuntu,
+}
+
+impl fmt::Display for Distro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Debian => write!(f, "Debian"),
+            Self::Ubuntu => write!(f, "Ubuntu"),
+        }
+    }
+}
+
+/// A release a suite refers to, e.g. `jammy` for `jammy-updates`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Release {
+    pub distro: Distro,
+    pub codename: String,
+    /// Position in the release history. Development suites such as `sid` have none.
+    pub order: Option<usize>,
+}
+
+impl Release {
+    /// Recognizes Debian and Ubuntu codenames, with or without a pocket suffix. Suites such as
+    /// `stable` or a vendor's `nodistro` return `None`, since they don't name a release.
+    pub fn from_suite(suite: &str) -> Option<Self> {
+        let suite = suite.trim_end_matches('/');
+        let codename = POCKETS
+            .iter()
+            .find_map(|pocket| suite.strip_suffix(pocket))
+            .unwrap_or(suite);
+        Self::from_codename(codename)
+    }
+
+    /// Like [`Release::from_suite`], also resolving Debian's `stable`, `testing`, ... when
+    /// `uri` is a Debian archive. Other vendors use `stable` for their own channel.
+    pub fn from_source_suite(uri: &str, suite: &str) -> Option<Self> {
+        Self::from_suite(suite).or_else(|| {
+            if !is_debian_archive(uri) {
+                return None;
+            }
+            let suite = suite.trim_end_matches('/');
+            let alias = POCKETS
+                .iter()
+                .find_map(|pocket| suite.strip_suffix(pocket))
+                .unwrap_or(suite);
+            Self::from_codename(debian_alias(alias)?)
+        })
+    }
+
+    pub fn from_codename(codename: &str) -> Option<Self> {
+        let codename = codename.to_ascii_lowercase();
+        let position = |codenames: &[&str]| codenames.iter().position(|c| *c == codename);
+        let (distro, order) = if let Some(order) = position(&DEBIAN_CODENAMES) {
+            (Distro::Debian, Some(order))
+        } else if let Some(offset) = position(&DEBIAN_FUTURE_CODENAMES) {
+            (Distro::Debian, Some(DEBIAN_CODENAMES.len() + offset))
+        } else if DEBIAN_DEVELOPMENT_SUITES.contains(&codename.as_str()) {
+            (Distro::Debian, None)
+        } else if let Some(order) = position(&UBUNTU_CODENAMES) {
+            (Distro::Ubuntu, Some(order))
+        } else {
+            return None;
+        };
+        Some(Self {
+            distro,
+            codename,
+            order,
+        })
+    }
+
+    /// The release after this one, if it has a codename yet.
+    pub fn successor(&self) -> Option<Self> {
+        let next = self.order? + 1;
+        let codename = match self.distro {
+            Distro::Debian => DEBIAN_CODENAMES
+                .iter()
+                .chain(&DEBIAN_FUTURE_CODENAMES)
+                .nth(next),
+            Distro::Ubuntu => UBUNTU_CODENAMES.get(next),
+        }?;
+        Self::from_codename(codename)
+    }
+
+    /// Name used when talking about mixed releases. `sid` and `unstable` are the same suite.
+    fn mix_name(&self) -> &str {
+        match self.codename.as_str() {
+            "sid" => "unstable",
+            codename => codename,
+        }
+    }
+}
+
+/// The codename Debian's status names stand for, as of the last release in the tables above.
+fn debian_alias(suite: &str) -> Option<&'static str> {
+    let released = DEBIAN_CODENAMES.iter().rev();
+    match suite {
+        "stable" => released.clone().next(),
+        "oldstable" => released.clone().nth(1),
+        "oldoldstable" => released.clone().nth(2),
+        "testing" => DEBIAN_FUTURE_CODENAMES.first(),
+        _ => None,
+    }
+    .copied()
+}
+
+/// Whether `uri` is on one of Debian's own hosts, e.g. `deb.debian.org` or
+/// `security.debian.org`.
+pub(crate) fn is_debian_archive(uri: &str) -> bool {
+    let uri = strip_credentials(uri);
+    let rest = uri.split_once("://").map_or(uri.as_str(), |(_, rest)| rest);
+    let host = rest.split('/').next().unwrap_or_default();
+    let host = host.split_once(':').map_or(host, |(host, _)| host);
+    let host = host.to_ascii_lowercase();
+    host == "debian.org" || host.ends_with(".debian.org")
+}
+
+/// The parts of `/etc/os-release` that say which release is running.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OsRelease {
+    pub id: String,
+    pub pretty_name: String,
+    pub version_codename: Option<String>,
+    /// Set by Ubuntu and its derivatives, whose own `VERSION_CODENAME` (e.g. Mint's `wilma`)
+    /// is not what their sources use.
+    pub ubuntu_codename: Option<String>,
+}
+
+impl OsRelease {
+    pub fn parse(content: &str) -> Self {
+        let mut os_release = Self::default();
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            let value = (!value.is_empty()).then(|| value.to_string());
+            match key.trim() {
+                "ID" => os_release.id = value.unwrap_or_default(),
+                "PRETTY_NAME" => os_release.pretty_name = value.unwrap_or_default(),
+                "VERSION_CODENAME" => os_release.version_codename = value,
+                "UBUNTU_CODENAME" => os_release.ubuntu_codename = value,
+                _ => {}
+            }
+        }
+        os_release
+    }
+
+    /// Reads the running system's release, or an empty one when neither file can be read.
+    pub fn load() -> Self {
+        for path in OS_RELEASE_PATHS {
+            match fs::read_to_string(path) {
+                Ok(content) => return Self::parse(&content),
+                Err(e) => warn!("Unable to read {path}: {e}"),
+            }
+        }
+        Self::default()
+    }
+
+    /// The codename the system's sources are expected to use.
+    pub fn codename(&self) -> Option<&str> {
+        self.ubuntu_codename
+            .as_deref()
+            .or(self.version_codename.as_deref())
+    }
+
+    /// The running release, if it is a Debian or Ubuntu release this module knows.
+    pub fn release(&self) -> Option<Release> {
+        self.codename().and_then(Release::from_codename)
+    }
+}
+
+/// Why a source's suite doesn't fit the running system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SuiteIssue {
+    /// An older release of the same distribution, usually left behind by an upgrade.
+    Older { suite: String, running: String },
+    /// A release newer than the running one.
+    Newer { suite: String, running: String },
+    /// A release of another distribution, e.g. a Debian codename on Ubuntu.
+    OtherDistro {
+        suite: String,
+        distro: Distro,
+        running: String,
+    },
+    /// Enabled Debian sources from different releases, a "Frankendebian".
+    Mixed { releases: Vec<String> },
+}
+
+impl SuiteIssue {
+    /// A few characters for the list of sources.
+    pub fn badge(&self) -> &'static str {
+        match self {
+            Self::Older { .. } => "old",
+            Self::Newer { .. } => "newer",
+            Self::OtherDistro { .. } => "distro",
+            Self::Mixed { .. } => "mix",
+        }
+    }
+}
+
+impl fmt::Display for SuiteIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Older { suite, running } => write!(
+                f,
+                "{suite} is older than the running {running}, probably left over from an upgrade"
+            ),
+            Self::Newer { suite, running } => {
+                write!(f, "{suite} is newer than the running {running}")
+            }
+            Self::OtherDistro {
+                suite,
+                distro,
+                running,
+            } => write!(
+                f,
+                "{suite} is a {distro} release, but this system runs {running}"
+            ),
+            Self::Mixed { releases } => write!(
+                f,
+                "enabled sources mix {}, which can break upgrades",
+                releases.join(" and ")
+            ),
+        }
+    }
+}
+
+impl AptRepositories {
+    /// Compares every suite against the running release, and the enabled Debian suites against
+    /// each other.
+    pub fn suite_issues(&self, os_release: &OsRelease) -> Vec<(SourceRef, SuiteIssue)> {
+        let running = os_release.release();
+        let mut issues = Vec::new();
+        let mut enabled_releases = Vec::new();
+
+        for (source_ref, _, source) in self.source_refs() {
+            let Some(release) = Release::from_source_suite(&source.uri, &source.suite) else {
+                continue;
+            };
+            if let Some(running) = &running {
+                let suite = source.suite.clone();
+                let running_name = running.codename.clone();
+                if release.distro != running.distro {
+                    issues.push((
+                        source_ref,
+                        SuiteIssue::OtherDistro {
+                            suite,
+                            distro: release.distro,
+                            running: running_name,
+                        },
+                    ));
+                } else if let (Some(order), Some(running_order)) = (release.order, running.order) {
+                    if order < running_order {
+                        issues.push((
+                            source_ref,
+                            SuiteIssue::Older {
+                                suite,
+                                running: running_name,
+                            },
+                        ));
+                    } else if order > running_order {
+                        issues.push((
+                            source_ref,
+                            SuiteIssue::Newer {
+                                suite,
+                                running: running_name,
+                            },
+                        ));
+                    }
+                }
+            }
+            if source.enabled && release.distro == Distro::Debian {
+                enabled_releases.push((source_ref, release));
+            }
+        }
+
+        // experimental only makes sense next to unstable, so it doesn't count as a mix
+        let releases: BTreeSet<&str> = enabled_releases
+            .iter()
+            .map(|(_, release)| release.mix_name())
+            .filter(|name| *name != "experimental" && *name != "rc-buggy")
+            .collect();
+        if releases.len() > 1 {
+            let releases: Vec<String> = releases.into_iter().map(str::to_string).collect();
+            issues.extend(enabled_releases.iter().map(|(source_ref, _)| {
+                (
+                    *source_ref,
+                    SuiteIssue::Mixed {
+                        releases: releases.clone(),
+                    },
+                )
+            }));
+        }
+
+        issues.sort_by_key(|(source_ref, _)| *source_ref);
+        issues
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::repositories;
+
+    #[test]
+    fn test_parse_os_release() {
+        let os_release = OsRelease::parse(
+            "NAME=\"Linux Mint\"\nID=linuxmint\nVERSION_CODENAME=wilma\nUBUNTU_CODENAME=noble\n",
+        );
+        assert_eq!(os_release.id, "linuxmint");
+        assert_eq!(os_release.codename(), Some("noble"));
+        assert_eq!(
+            os_release.release(),
+            Some(Release {
+                distro: Distro::Ubuntu,
+                codename: "noble".to_string(),
+                order: Some(39),
+            })
+        );
+        assert_eq!(
+            Release::from_suite("jammy-security").unwrap().codename,
+            "jammy"
+        );
+        assert_eq!(Release::from_suite("stable"), None);
+        assert_eq!(
+            Release::from_codename("bookworm")
+                .and_then(|release| release.successor())
+                .map(|release| release.codename),
+            Some("trixie".to_string())
+        );
+    }
+
+    #[test]
+    fn test_suite_issues() {
+        let repositories = repositories()
+            .lines(
+                "/etc/apt/sources.list",
+                &[
+                    "deb http://deb.debian.org/debian bookworm main",
+                    "deb http://deb.debian.org/debian bookworm-updates main",
+                    "deb http://deb.debian.org/debian bullseye-backports main",
+                    "deb http://deb.debian.org/debian sid main",
+                    "deb http://ppa.launchpadcontent.net/foo/bar/ubuntu jammy main",
+                    "deb https://deb.nodesource.com/node_20.x nodistro main",
+                ],
+            )
+            .build();
+        let os_release = OsRelease::parse("ID=debian\nVERSION_CODENAME=bookworm\n");
+        let issues: Vec<(usize, &str)> = repositories
+            .suite_issues(&os_release)
+            .iter()
+            .map(|(source_ref, issue)| (source_ref.source, issue.badge()))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (0, "mix"),
+                (1, "mix"),
+                (2, "old"),
+                (2, "mix"),
+                (3, "mix"),
+                (4, "distro"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_suite_aliases() {
+        assert_eq!(
+            Release::from_source_suite("http://deb.debian.org/debian", "oldstable-updates")
+                .map(|release| release.codename),
+            Some("bookworm".to_string())
+        );
+        assert_eq!(
+            Release::from_source_suite("https://download.docker.com/linux/debian", "stable"),
+            None
+        );
+        let repositories = repositories()
+            .lines(
+                "/etc/apt/sources.list",
+                &[
+                    "deb http://deb.debian.org/debian stable main",
+                    "deb http://deb.debian.org/debian sid main",
+                    "deb https://download.docker.com/linux/debian stable main",
+                ],
+            )
+            .build();
+        let issues = repositories.suite_issues(&OsRelease::default());
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    SourceRef { list: 0, source: 0 },
+                    SuiteIssue::Mixed {
+                        releases: vec!["trixie".to_string(), "unstable".to_string()]
+                    }
+                ),
+                (
+                    SourceRef { list: 0, source: 1 },
+                    SuiteIssue::Mixed {
+                        releases: vec!["trixie".to_string(), "unstable".to_string()]
+                    }
+                ),
+            ]
+        );
+    }
+}
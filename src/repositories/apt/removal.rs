@@ -0,0 +1,149 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{read_lossy, AptRepositories, AptSource};
+use crate::repositories::Repository;
+
+/// Everything shown before a source file is deleted.
+#[derive(Debug, Default, Clone)]
+pub struct RemovalPreview {
+    pub file_path: PathBuf,
+    pub content: String,
+    /// The entries in the file, in one-line form.
+    pub entries: Vec<String>,
+    /// `signed-by` keyrings used by this file and by no other loaded source.
+    pub unused_keyrings: Vec<String>,
+}
+
+fn keyrings<'a>(sources: impl Iterator<Item = &'a AptSource>) -> BTreeSet<&'a str> {
+    sources.filter_map(AptSource::signed_by_path).collect()
+}
+
+impl AptRepositories {
+    /// Collects what removing the list at `list_index` would delete.
+    pub fn preview_removal(&self, list_index: usize) -> Result<RemovalPreview> {
+        let apt_list = self
+            .items
+            .get(list_index)
+            .ok_or_else(|| eyre!("No source file at index {list_index}"))?;
+        if apt_list.primary {
+            return Err(eyre!(
+                "{} is the primary list and can't be removed",
+                apt_list.file_path.display()
+            ));
+        }
+
+        let in_use = keyrings(
+            self.items
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| *index != list_index)
+                .flat_map(|(_, other)| other.sources.iter()),
+        );
+        let unused_keyrings = keyrings(apt_list.sources.iter())
+            .difference(&in_use)
+            .map(|keyring| keyring.to_string())
+            .collect();
+
+        Ok(RemovalPreview {
+            file_path: apt_list.file_path.clone(),
+            content: read_lossy(&apt_list.file_path)?,
+            entries: apt_list
+                .sources
+                .iter()
+                .map(|source| {
+                    let prefix = if source.enabled { "" } else { "# " };
+                    format!("{prefix}{source}")
+                })
+                .collect(),
+            unused_keyrings,
+        })
+    }
+
+    /// Deletes a source file from `sources.list.d` and reloads the lists.
+    pub fn remove_list(&mut self, file_path: &Path) -> Result<()> {
+        let Some(apt_list) = self
+            .items
+            .iter()
+            .find(|apt_list| apt_list.file_path == file_path)
+        else {
+            return Err(eyre!("{} is not a loaded source file", file_path.display()));
+        };
+        if apt_list.primary {
+            return Err(eyre!(
+                "{} is the primary list and can't be removed",
+                file_path.display()
+            ));
+        }
+        fs::remove_file(file_path)
+            .map_err(|e| eyre!("Failed to remove {}: {e}", file_path.display()))?;
+        self.load_repository_list()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::repositories::apt::test_support::{self, TempDir};
+
+    #[test]
+    fn test_preview_and_remove_list() {
+        let dir = TempDir::new("removal");
+        let sources_dir = dir.join("sources.list.d");
+        fs::create_dir(&sources_dir).unwrap();
+        fs::write(
+            dir.join("sources.list"),
+            "deb http://deb.debian.org/debian bookworm main\n",
+        )
+        .unwrap();
+        fs::write(
+            sources_dir.join("other.list"),
+            "deb [signed-by=/etc/apt/keyrings/shared.gpg] https://other.example/apt stable main\n",
+        )
+        .unwrap();
+        // A Latin-1 comment, which apt reads all the same
+        let mut vendor = b"# Fran\xe7ois' mirror\n".to_vec();
+        vendor.extend_from_slice(
+            b"deb [signed-by=/etc/apt/keyrings/shared.gpg] https://vendor.example/apt stable main\n\
+              # deb [signed-by=/etc/apt/keyrings/vendor.gpg] https://vendor.example/apt beta main\n",
+        );
+        let vendor_path = sources_dir.join("vendor.list");
+        fs::write(&vendor_path, &vendor).unwrap();
+        let mut repositories = test_support::load(&dir);
+        assert_eq!(repositories.items.len(), 3);
+
+        let preview = repositories.preview_removal(2).unwrap();
+        assert_eq!(preview.file_path, vendor_path);
+        assert!(preview.content.starts_with("# Fran\u{fffd}ois' mirror\n"));
+        assert_eq!(preview.entries.len(), 2);
+        // shared.gpg still signs other.list
+        assert_eq!(
+            preview.unused_keyrings,
+            vec!["/etc/apt/keyrings/vendor.gpg"]
+        );
+
+        assert_eq!(
+            repositories.preview_removal(0).unwrap_err().to_string(),
+            format!(
+                "{} is the primary list and can't be removed",
+                dir.join("sources.list").display()
+            )
+        );
+        assert!(repositories.remove_list(&dir.join("sources.list")).is_err());
+        assert!(dir.join("sources.list").exists());
+
+        repositories.remove_list(&vendor_path).unwrap();
+        assert!(!vendor_path.exists());
+        assert_eq!(repositories.items.len(), 2);
+        let preview = repositories.preview_removal(1).unwrap();
+        assert_eq!(
+            preview.unused_keyrings,
+            vec!["/etc/apt/keyrings/shared.gpg"]
+        );
+    }
+}
@@ -0,0 +1,208 @@
+use color_eyre::{eyre::eyre, Result};
+use std::fs;
+use std::path::PathBuf;
+
+use super::{
+    edit,
+    release::{Release, POCKETS},
+    AptRepositories,
+};
+use crate::repositories::Repository;
+
+/// Hosts of the Debian and Ubuntu archives, which publish every release.
+const ARCHIVE_HOSTS: [&str; 4] = [
+    "debian.org",
+    "archive.ubuntu.com",
+    "security.ubuntu.com",
+    "ports.ubuntu.com",
+];
+
+/// `to` for `from`, and `to-updates` for `from-updates` and so on for the other pockets.
+pub fn rewrite_suite(suite: &str, from: &str, to: &str) -> Option<String> {
+    if suite == from {
+        return Some(to.to_string());
+    }
+    POCKETS
+        .iter()
+        .find(|pocket| suite.strip_suffix(*pocket) == Some(from))
+        .map(|pocket| format!("{to}{pocket}"))
+}
+
+/// Whether the repository at `uri` is known to publish `suite`. Only the distribution
+/// archives are; a vendor may or may not have caught up with a new release.
+fn publishes(uri: &str, suite: &str) -> bool {
+    let host = uri
+        .split_once("://")
+        .map_or(uri, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let is_archive = ARCHIVE_HOSTS
+        .iter()
+        .any(|archive| host == *archive || host.ends_with(&format!(".{archive}")));
+    is_archive && Release::from_suite(suite).is_some()
+}
+
+/// The new content of one file.
+#[derive(Debug, Default, Clone)]
+pub struct FileRewrite {
+    pub file_path: PathBuf,
+    pub original: String,
+    pub updated: String,
+    /// URIs of the file's repositories that may not publish the new suite.
+    pub unknown_targets: Vec<String>,
+    /// Left as it is when the rewrite is applied. Files with unknown targets start skipped.
+    pub skip: bool,
+}
+
+impl FileRewrite {
+    /// Changed lines as `(line number, old, new)`. Renaming never adds or removes lines.
+    pub fn diff(&self) -> Vec<(usize, &str, &str)> {
+        self.original
+            .lines()
+            .zip(self.updated.lines())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(index, (old, new))| (index + 1, old, new))
+            .collect()
+    }
+}
+
+/// Renaming the suites of one release to another in every file that uses them.
+#[derive(Debug, Default, Clone)]
+pub struct CodenameRewrite {
+    pub from: String,
+    pub to: String,
+    pub files: Vec<FileRewrite>,
+}
+
+impl AptRepositories {
+    /// Sources, enabled or not, whose suite would be renamed from `from` to `to`.
+    pub fn count_codename_uses(&self, from: &str) -> usize {
+        self.sources()
+            .filter(|(_, source)| rewrite_suite(&source.suite, from, "").is_some())
+            .count()
+    }
+
+    /// Computes the new content of every file with a suite of `from`, without writing anything.
+    pub fn plan_codename_rewrite(&self, from: &str, to: &str) -> Result<CodenameRewrite> {
+        let rename = |suite: &str| rewrite_suite(suite, from, to);
+        let mut plan = CodenameRewrite {
+            from: from.to_string(),
+            to: to.to_string(),
+            files: Vec::new(),
+        };
+
+        for apt_list in &self.items {
+            let mut sources: Vec<_> = apt_list
+                .sources
+                .iter()
+                .filter(|source| rename(&source.suite).is_some())
+                .collect();
+            if sources.is_empty() {
+                continue;
+            }
+            // A deb822 stanza is rewritten once for all of its suites
+            sources.dedup_by_key(|source| source.line_number);
+
+            let original = fs::read_to_string(&apt_list.file_path)?;
+            let mut updated = original.clone();
+            for source in &sources {
+                updated = edit::rename_suite_in_content(&updated, apt_list.format, source, &rename)
+                    .map_err(|e| eyre!("{}: {e}", apt_list.file_path.display()))?;
+            }
+
+            let mut unknown_targets: Vec<String> = apt_list
+                .sources
+                .iter()
+                .filter_map(|source| {
+                    rename(&source.suite)
+                        .filter(|suite| !publishes(&source.uri, suite))
+                        .map(|_| source.uri.clone())
+                })
+                .collect();
+            unknown_targets.dedup();
+
+            plan.files.push(FileRewrite {
+                file_path: apt_list.file_path.clone(),
+                original,
+                skip: !unknown_targets.is_empty(),
+                unknown_targets,
+                updated,
+            });
+        }
+        Ok(plan)
+    }
+
+    /// Writes the files of `plan` that aren't skipped and reloads the lists. Nothing is written
+    /// when any of them changed since the plan was made.
+    pub fn apply_codename_rewrite(&mut self, plan: &CodenameRewrite) -> Result<usize> {
+        let files: Vec<&FileRewrite> = plan.files.iter().filter(|file| !file.skip).collect();
+        for file in &files {
+            if fs::read_to_string(&file.file_path)? != file.original {
+                return Err(eyre!(
+                    "{} changed since the preview",
+                    file.file_path.display()
+                ));
+            }
+        }
+        for file in &files {
+            edit::write_atomic(&file.file_path, &file.updated)?;
+        }
+        self.load_repository_list()?;
+        Ok(files.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_rewrite_suite() {
+        assert_eq!(
+            rewrite_suite("bookworm", "bookworm", "trixie"),
+            Some("trixie".to_string())
+        );
+        assert_eq!(
+            rewrite_suite("bookworm-security", "bookworm", "trixie"),
+            Some("trixie-security".to_string())
+        );
+        assert_eq!(rewrite_suite("bookworm-foo", "bookworm", "trixie"), None);
+        assert_eq!(
+            rewrite_suite("cloud-sdk-bookworm", "bookworm", "trixie"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_publishes() {
+        assert!(publishes("http://deb.debian.org/debian", "trixie-updates"));
+        assert!(publishes("http://de.archive.ubuntu.com/ubuntu", "noble"));
+        assert!(!publishes(
+            "https://download.docker.com/linux/debian",
+            "trixie"
+        ));
+        assert!(!publishes("http://deb.debian.org/debian", "bookworn"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let file = FileRewrite {
+            original: "# comment\ndeb http://deb.debian.org/debian bookworm main\n".to_string(),
+            updated: "# comment\ndeb http://deb.debian.org/debian trixie main\n".to_string(),
+            ..FileRewrite::default()
+        };
+        assert_eq!(
+            file.diff(),
+            vec![(
+                2,
+                "deb http://deb.debian.org/debian bookworm main",
+                "deb http://deb.debian.org/debian trixie main"
+            )]
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use super::{
+    apt_conf::{AptConfig, ConfigValue},
+    lint::{Diagnostic, Severity},
+    preferences::{Pattern, PinTarget},
+    release::OsRelease,
+    SourceRef,
+};
+
+pub const ORIGINS_PATTERN_KEY: &str = "Unattended-Upgrade::Origins-Pattern";
+pub const ALLOWED_ORIGINS_KEY: &str = "Unattended-Upgrade::Allowed-Origins";
+/// Whether the daily apt job runs unattended-upgrade at all.
+pub const PERIODIC_KEY: &str = "APT::Periodic::Unattended-Upgrade";
+
+type ParseOrigin = fn(&ConfigValue, &[(&str, &str)]) -> Option<OriginPattern>;
+
+/// A field of the Release file an origin pattern compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OriginField {
+    Origin,
+    Label,
+    Archive,
+    Codename,
+    /// `Archive` or `Codename`, which the archive of an Allowed-Origins entry can be either of.
+    Suite,
+    Component,
+    Site,
+}
+
+/// An entry of `Unattended-Upgrade::Origins-Pattern` or `Allowed-Origins`, with the
+/// `${distro_id}` and `${distro_codename}` variables replaced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OriginPattern {
+    pub definition: ConfigValue,
+    conditions: Vec<(OriginField, Pattern)>,
+}
+
+impl OriginPattern {
+    /// Parses `origin=Debian,codename=${distro_codename},label=Debian-Security`, where a
+    /// comma in a value is escaped as `\,`.
+    fn parse_pattern(definition: &ConfigValue, variables: &[(&str, &str)]) -> Option<Self> {
+        let value = substitute(&definition.value, variables).replace("\\,", "\u{0}");
+        let mut conditions = Vec::new();
+        for part in value.split(',') {
+            let (key, value) = part.split_once('=')?;
+            let field = match key.trim() {
+                "origin" | "o" => OriginField::Origin,
+                "label" | "l" => OriginField::Label,
+                "archive" | "suite" | "a" => OriginField::Archive,
+                "codename" | "n" => OriginField::Codename,
+                "component" | "c" => OriginField::Component,
+                "site" => OriginField::Site,
+                _ => return None,
+            };
+            let value = value.trim().replace('\u{0}', ",");
+            conditions.push((field, Pattern::parse(&value).ok()?));
+        }
+        Some(Self {
+            definition: definition.clone(),
+            conditions,
+        })
+    }
+
+    /// Parses the older `origin:archive` form, with `\:` for a colon in the origin.
+    fn parse_allowed(definition: &ConfigValue, variables: &[(&str, &str)]) -> Option<Self> {
+        let value = substitute(&definition.value, variables).replace("\\:", "\u{0}");
+        let (origin, archive) = value.rsplit_once(':')?;
+        let origin = origin.replace('\u{0}', ":");
+        Some(Self {
+            definition: definition.clone(),
+            conditions: vec![
+                (OriginField::Origin, Pattern::parse(&origin).ok()?),
+                (OriginField::Suite, Pattern::parse(archive).ok()?),
+            ],
+        })
+    }
+
+    pub fn matches(&self, target: &PinTarget) -> bool {
+        self.conditions.iter().all(|(field, pattern)| {
+            let matches = |value: &Option<String>| {
+                value.as_deref().is_some_and(|value| pattern.matches(value))
+            };
+            match field {
+                OriginField::Origin => matches(&target.origin),
+                OriginField::Label => matches(&target.label),
+                OriginField::Archive => matches(&target.archive),
+                OriginField::Codename => matches(&target.codename),
+                OriginField::Suite => matches(&target.archive) || matches(&target.codename),
+                OriginField::Component => target
+                    .components
+                    .iter()
+                    .any(|component| pattern.matches(component)),
+                OriginField::Site => pattern.matches(&target.host),
+            }
+        })
+    }
+}
+
+fn substitute(value: &str, variables: &[(&str, &str)]) -> String {
+    variables
+        .iter()
+        .fold(value.to_string(), |value, (name, replacement)| {
+            value.replace(&format!("${{{name}}}"), replacement)
+        })
+}
+
+/// The `${distro_id}` and `${distro_codename}` unattended-upgrades replaces, as `lsb_release`
+/// reports them.
+fn variables(os_release: &OsRelease) -> Vec<(&'static str, String)> {
+    let mut id = os_release.id.clone();
+    if let Some(first) = id.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    vec![
+        ("distro_id", id),
+        (
+            "distro_codename",
+            os_release.codename().unwrap_or_default().to_string(),
+        ),
+    ]
+}
+
+/// What unattended-upgrades is configured to do, and which sources it upgrades from.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UnattendedUpgrades {
+    /// The value of `APT::Periodic::Unattended-Upgrade`, `None` when it isn't set.
+    pub periodic: Option<String>,
+    pub patterns: Vec<OriginPattern>,
+    /// The patterns that select each enabled source, by index into `patterns`.
+    pub coverage: HashMap<SourceRef, Vec<usize>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl UnattendedUpgrades {
+    pub fn new(
+        config: &AptConfig,
+        os_release: &OsRelease,
+        targets: &HashMap<SourceRef, PinTarget>,
+    ) -> Self {
+        let variables = variables(os_release);
+        let variables: Vec<(&str, &str)> = variables
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        let mut patterns = Vec::new();
+        let mut diagnostics = Vec::new();
+        let parsers: [(&str, ParseOrigin); 2] = [
+            (ORIGINS_PATTERN_KEY, OriginPattern::parse_pattern),
+            (ALLOWED_ORIGINS_KEY, OriginPattern::parse_allowed),
+        ];
+        for (key, parse) in parsers {
+            for definition in config.list(key) {
+                match parse(definition, &variables) {
+                    Some(pattern) => patterns.push(pattern),
+                    None => diagnostics.push(Diagnostic {
+                        path: definition.path.clone(),
+                        line: Some(definition.line),
+                        severity: Severity::Warning,
+                        code: "invalid-origin",
+                        message: format!("unattended-upgrades can't use \"{}\"", definition.value),
+                    }),
+                }
+            }
+        }
+        let coverage = targets
+            .iter()
+            .map(|(source_ref, target)| {
+                let matching = patterns
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, pattern)| pattern.matches(target))
+                    .map(|(index, _)| index)
+                    .collect();
+                (*source_ref, matching)
+            })
+            .collect::<HashMap<SourceRef, Vec<usize>>>();
+        for (index, pattern) in patterns.iter().enumerate() {
+            if !coverage.values().any(|matching| matching.contains(&index)) {
+                diagnostics.push(Diagnostic {
+                    path: pattern.definition.path.clone(),
+                    line: Some(pattern.definition.line),
+                    severity: Severity::Warning,
+                    code: "origin-matches-nothing",
+                    message: format!("\"{}\" matches no enabled source", pattern.definition.value),
+                });
+            }
+        }
+        Self {
+            periodic: config
+                .get(PERIODIC_KEY)
+                .map(|setting| setting.effective().value.clone()),
+            patterns,
+            coverage,
+            diagnostics,
+        }
+    }
+
+    /// Whether the daily job runs unattended-upgrade: the interval is a number of days,
+    /// `0` turns it off.
+    pub fn is_enabled(&self) -> bool {
+        self.periodic
+            .as_deref()
+            .is_some_and(|value| !matches!(value.trim(), "" | "0" | "false" | "no"))
+    }
+
+    /// The patterns that select `source_ref`, empty when it gets no automatic upgrades.
+    pub fn patterns_of(&self, source_ref: SourceRef) -> Vec<&OriginPattern> {
+        self.coverage
+            .get(&source_ref)
+            .map(|matching| {
+                matching
+                    .iter()
+                    .map(|index| &self.patterns[*index])
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use std::path::Path;
+
+    use super::*;
+    use crate::repositories::apt::{apt_conf::parse_config, AptSource};
+
+    const CONFIG: &str = r#"
+APT::Periodic::Unattended-Upgrade "1";
+Unattended-Upgrade::Origins-Pattern {
+        "origin=Debian,codename=${distro_codename}-updates";
+        "origin=Debian,codename=${distro_codename},label=Debian-Security";
+        "origin=Debian,codename=${distro_codename}-security,label=Debian-Security";
+        "site=deb.nodesource.com";
+        "origin=Unknown\, Inc.,suite=stable";
+};
+Unattended-Upgrade::Allowed-Origins {
+        "${distro_id}:${distro_codename}";
+        "broken";
+};
+"#;
+
+    fn target(suite: &str, release: &str) -> PinTarget {
+        let source = AptSource {
+            enabled: true,
+            uri: "http://deb.debian.org/debian".to_string(),
+            suite: suite.to_string(),
+            components: vec!["main".to_string()],
+            ..AptSource::default()
+        };
+        PinTarget::new(&source, Some(release))
+    }
+
+    #[test]
+    fn test_unattended_coverage() {
+        let (statements, _) = parse_config(Path::new("50unattended-upgrades"), CONFIG);
+        let config = AptConfig::merge(statements, Vec::new());
+        let os_release = OsRelease::parse("ID=debian\nVERSION_CODENAME=bookworm\n");
+        let refs: Vec<SourceRef> = (0..3).map(|source| SourceRef { list: 0, source }).collect();
+        let targets = HashMap::from([
+            (
+                refs[0],
+                target(
+                    "bookworm",
+                    "Origin: Debian\nLabel: Debian\nSuite: stable\nCodename: bookworm\n",
+                ),
+            ),
+            (
+                refs[1],
+                target(
+                    "bookworm-security",
+                    "Origin: Debian\nLabel: Debian-Security\nSuite: stable-security\n\
+                     Codename: bookworm-security\n",
+                ),
+            ),
+            (
+                refs[2],
+                target(
+                    "bookworm-backports",
+                    "Origin: Debian Backports\nSuite: stable-backports\n\
+                     Codename: bookworm-backports\n",
+                ),
+            ),
+        ]);
+        let unattended = UnattendedUpgrades::new(&config, &os_release, &targets);
+        assert!(unattended.is_enabled());
+        let covered = |source_ref| -> Vec<String> {
+            unattended
+                .patterns_of(source_ref)
+                .iter()
+                .map(|pattern| pattern.definition.value.clone())
+                .collect()
+        };
+        assert_eq!(covered(refs[0]), vec!["${distro_id}:${distro_codename}"]);
+        assert_eq!(
+            covered(refs[1]),
+            vec!["origin=Debian,codename=${distro_codename}-security,label=Debian-Security"]
+        );
+        assert_eq!(covered(refs[2]), Vec::<String>::new());
+        let problems: Vec<(usize, &str)> = unattended
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line.unwrap(), diagnostic.code))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (12, "invalid-origin"),
+                (4, "origin-matches-nothing"),
+                (5, "origin-matches-nothing"),
+                (7, "origin-matches-nothing"),
+                (8, "origin-matches-nothing"),
+            ]
+        );
+    }
+}
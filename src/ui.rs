@@ -15,11 +15,13 @@ pub enum Mode {
     #[default]
     MainMenu,
     System,
+    AptConfig,
     InstalledPackageTabs,
     InstalledPackageList,
     PackageSourceTabs,
     PackageSourceList,
     ConfirmationPopup,
+    TextInput,
 }
 
 impl fmt::Display for Mode {
@@ -33,6 +35,7 @@ pub enum Page {
     #[default]
     System,
     PackageSources,
+    AptConfig,
     InstalledPackages,
     ConfirmationPopup,
 }
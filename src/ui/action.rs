@@ -13,6 +13,40 @@ pub enum ListAction {
     MakeSelection,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum SourceAction {
+    NextView,
+    AddSource,
+    RemoveFile,
+    Consolidate,
+    RewriteCodename,
+    ConvertFile,
+    ConvertAll,
+    MigrateLegacyKeys,
+    CleanLists,
+    ShowInstalled,
+    DisableUnused,
+    ApplyFix,
+    ApplyAllFixes,
+    UndoFix,
+    MoveCredentials,
+    EditComponents,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum SystemAction {
+    FixPermission,
+    FixAllPermissions,
+    Refresh,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum ConfigAction {
+    NextView,
+    EditValue,
+    Refresh,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
 pub enum Action {
     Tick,
@@ -29,4 +63,11 @@ pub enum Action {
     FocusMainMenu,
     NextMode,
     PrevMode,
+    SourceAction(SourceAction),
+    SystemAction(SystemAction),
+    ConfigAction(ConfigAction),
+    Confirm,
+    Cancel,
+    NextField,
+    PrevField,
 }
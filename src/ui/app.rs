@@ -13,7 +13,7 @@ use crate::{
     config::Config,
     ui::action::Action,
     ui::components::{
-        installed_packages::InstalledPackages, main_menu::MainMenu,
+        apt_config_page::AptConfigPage, installed_packages::InstalledPackages, main_menu::MainMenu,
         package_sources::PackageSources, system_page::SystemPage, Component,
     },
     ui::tui::{Event, Tui},
@@ -45,6 +45,7 @@ impl App {
                 Box::new(MainMenu::new()),
                 Box::new(PackageSources::new()),
                 Box::new(SystemPage::new()),
+                Box::new(AptConfigPage::new()),
             ],
             should_quit: false,
             should_suspend: false,
@@ -123,6 +124,13 @@ impl App {
             let Some(universal_keybinds) = self.config.keybindings.get(&Mode::Universal) else {
                 return Ok(());
             };
+            // While typing, only the keys bound for text input are actions. Everything else
+            // goes to the component that owns the input.
+            let universal_keybinds = if self.view_state.mode == Mode::TextInput {
+                &HashMap::new()
+            } else {
+                universal_keybinds
+            };
             let list_keymap = if self.view_state.mode.to_string().ends_with("List")
                 || self.view_state.mode == Mode::MainMenu
             {
@@ -12,9 +12,12 @@ use crate::{config::Config, ui::action::Action, ui::tui::Event};
 
 use super::ViewState;
 
+pub(crate) mod apt_config_page;
+pub(crate) mod form;
 pub(crate) mod installed_packages;
 pub(crate) mod main_menu;
 pub(crate) mod package_sources;
+pub(crate) mod popup;
 pub(crate) mod system_page;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
@@ -114,7 +117,7 @@ pub trait Component {
     ///
     /// * `Result<Option<Action>>` - An action to be processed or none.
     fn update(&mut self, action: Action, view_state: ViewState) -> Result<Option<Action>> {
-        let _ = action; // to appease clippy
+        let _ = (action, view_state); // to appease clippy
         Ok(None)
     }
     /// Render the component on the screen. (REQUIRED)
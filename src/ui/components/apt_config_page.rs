@@ -0,0 +1,671 @@
+use std::collections::HashMap;
+
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Tabs, Wrap},
+    Frame,
+};
+use tracing::info;
+
+use crate::{
+    repositories::{
+        apt::{
+            apt_conf::{self, AptConfig, Setting},
+            lint::{Diagnostic, Severity},
+            permissions,
+            preferences::PinTarget,
+            release::OsRelease,
+            unattended::{UnattendedUpgrades, ORIGINS_PATTERN_KEY, PERIODIC_KEY},
+            AptRepositories, SourceRef, SourceType,
+        },
+        Repository,
+    },
+    ui::{
+        action::{Action, ConfigAction, ListAction},
+        Mode, Page, ViewState,
+    },
+};
+
+use super::{
+    form::{Form, FormField},
+    Component,
+};
+
+/// The views of the page, switched with `v`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ConfigView {
+    #[default]
+    Settings,
+    Unattended,
+}
+
+impl ConfigView {
+    const ALL: [ConfigView; 2] = [ConfigView::Settings, ConfigView::Unattended];
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Settings => "Settings",
+            Self::Unattended => "Unattended upgrades",
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|view| *view == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// A line of a table: an item of the view, or a problem found reading the files.
+#[derive(Debug, Clone, Copy)]
+enum PageRow {
+    Item(usize),
+    Problem(usize),
+}
+
+/// Outcome of the last change made from this page, shown under the list.
+enum StatusMessage {
+    Info(String),
+    Error(String),
+}
+
+fn severity_line(diagnostic: &Diagnostic) -> Line<'static> {
+    let line = Line::from(format!("{}: {}", diagnostic.severity, diagnostic.message));
+    match diagnostic.severity {
+        Severity::Error => line.red(),
+        Severity::Warning => line.yellow(),
+        Severity::Info => line,
+    }
+}
+
+fn problem_row(diagnostic: &Diagnostic) -> Row<'static> {
+    let location = Cell::from(diagnostic.location());
+    Row::new([
+        match diagnostic.severity {
+            Severity::Error => location.red(),
+            Severity::Warning => location.yellow(),
+            Severity::Info => location,
+        },
+        Cell::from(diagnostic.message.clone()),
+    ])
+    .style(Style::new().fg(Color::DarkGray))
+}
+
+/// The value of a setting as the table shows it: lists by their items.
+fn value_text(setting: &Setting) -> String {
+    if setting.is_list() {
+        let values = setting.values();
+        format!("[{}] {}", values.len(), values.join(", "))
+    } else {
+        setting.effective().value.clone()
+    }
+}
+
+/// The apt configuration page: the settings apt ends up with from `apt.conf.d`, and which
+/// sources unattended-upgrades upgrades from.
+#[derive(Default)]
+pub struct AptConfigPage {
+    repositories: AptRepositories,
+    os_release: OsRelease,
+    config: AptConfig,
+    unattended: UnattendedUpgrades,
+    /// What unattended-upgrades compares of each enabled `deb` source.
+    targets: HashMap<SourceRef, PinTarget>,
+    /// Enabled `deb` sources, in the order of the source files.
+    sources: Vec<SourceRef>,
+    view: ConfigView,
+    setting_rows: Vec<PageRow>,
+    source_rows: Vec<PageRow>,
+    settings_state: TableState,
+    sources_state: TableState,
+    status: Option<StatusMessage>,
+    /// The form open to change the setting at this index.
+    form: Option<(Form, usize)>,
+}
+
+impl AptConfigPage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn refresh(&mut self) {
+        if let Err(e) = self.repositories.load_repository_list() {
+            self.status = Some(StatusMessage::Error(e.to_string()));
+        }
+        self.os_release = OsRelease::load();
+        self.config = apt_conf::read_apt_config();
+        let lists = self.repositories.lists_status();
+        self.sources = self
+            .repositories
+            .source_refs()
+            .filter(|(_, _, source)| source.enabled && source.source_type == SourceType::Deb)
+            .map(|(source_ref, _, _)| source_ref)
+            .collect();
+        self.targets = self
+            .repositories
+            .pin_targets(&lists)
+            .into_iter()
+            .filter(|(source_ref, _)| self.sources.contains(source_ref))
+            .collect();
+        self.unattended = UnattendedUpgrades::new(&self.config, &self.os_release, &self.targets);
+
+        self.setting_rows = (0..self.config.settings.len()).map(PageRow::Item).collect();
+        self.setting_rows
+            .extend((0..self.config.diagnostics.len()).map(PageRow::Problem));
+        self.source_rows = (0..self.sources.len()).map(PageRow::Item).collect();
+        self.source_rows
+            .extend((0..self.unattended.diagnostics.len()).map(PageRow::Problem));
+        for (state, len) in [
+            (&mut self.settings_state, self.setting_rows.len()),
+            (&mut self.sources_state, self.source_rows.len()),
+        ] {
+            if state.selected().is_some_and(|selected| selected >= len) {
+                state.select(len.checked_sub(1));
+            }
+        }
+    }
+
+    fn table_state(&mut self) -> (&mut TableState, usize) {
+        match self.view {
+            ConfigView::Settings => (&mut self.settings_state, self.setting_rows.len()),
+            ConfigView::Unattended => (&mut self.sources_state, self.source_rows.len()),
+        }
+    }
+
+    fn handle_list_movement(&mut self, list_action: ListAction) {
+        self.status = None;
+        let (table_state, len) = self.table_state();
+        let last = len.saturating_sub(1);
+        let selected = table_state.selected();
+        match list_action {
+            ListAction::SelectNext => table_state.select(Some(match selected {
+                Some(selected) if selected < last => selected + 1,
+                _ => 0,
+            })),
+            ListAction::SelectPrev => table_state.select(Some(match selected {
+                Some(selected) if selected > 0 => selected - 1,
+                _ => last,
+            })),
+            ListAction::SelectFirst => table_state.select_first(),
+            ListAction::SelectLast => table_state.select(Some(last)),
+            ListAction::SelectNone => table_state.select(None),
+            ListAction::MakeSelection => {}
+        }
+    }
+
+    fn selected_setting(&self) -> Option<usize> {
+        match self.setting_rows.get(self.settings_state.selected()?)? {
+            PageRow::Item(index) => Some(*index),
+            PageRow::Problem(_) => None,
+        }
+    }
+
+    fn handle_config_action(&mut self, config_action: ConfigAction) -> Option<Action> {
+        self.status = None;
+        match config_action {
+            ConfigAction::NextView => {
+                self.view = self.view.next();
+                None
+            }
+            ConfigAction::Refresh => {
+                self.refresh();
+                None
+            }
+            ConfigAction::EditValue => self.open_edit_form(),
+        }
+    }
+
+    fn open_edit_form(&mut self) -> Option<Action> {
+        if self.view != ConfigView::Settings {
+            return None;
+        }
+        let index = self.selected_setting()?;
+        let setting = &self.config.settings[index];
+        let error = if setting.is_list() {
+            Some("Lists can't be edited here, change their items in the files")
+        } else if setting.effective().span.is_none() {
+            Some("The key has no value to change")
+        } else if !permissions::is_privileged() {
+            Some("Changing apt.conf.d needs root, run yacufu with sudo")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            self.status = Some(StatusMessage::Error(error.to_string()));
+            return None;
+        }
+        let fields = vec![FormField {
+            label: "Value",
+            value: setting.effective().value.clone(),
+            error: None,
+        }];
+        let title = format!(" {} ", setting.key);
+        self.form = Some((Form::new(title, fields), index));
+        self.validate_form();
+        Some(Action::UpdateViewState(ViewState::new(
+            Mode::TextInput,
+            Page::AptConfig,
+        )))
+    }
+
+    fn validate_form(&mut self) {
+        let Some((form, index)) = self.form.as_mut() else {
+            return;
+        };
+        let value = &form.fields[0].value;
+        form.fields[0].error = value
+            .contains('"')
+            .then(|| "no quotes, apt has no way to escape them".to_string());
+        let setting = &self.config.settings[*index];
+        let mut preview = vec![Line::from(format!(
+            "Will be changed in {}",
+            setting.effective().location()
+        ))
+        .bold()];
+        preview.extend(setting.definitions.iter().rev().skip(1).map(|definition| {
+            Line::from(format!(
+                "  overrides \"{}\" from {}",
+                definition.value,
+                definition.location()
+            ))
+            .dark_gray()
+        }));
+        form.set_preview(preview);
+    }
+
+    fn close_form(&mut self) -> Option<Action> {
+        self.form = None;
+        Some(Action::UpdateViewState(ViewState::new(
+            Mode::AptConfig,
+            Page::AptConfig,
+        )))
+    }
+
+    fn handle_form_action(&mut self, action: Action) -> Option<Action> {
+        let (form, index) = self.form.as_mut()?;
+        match action {
+            Action::Cancel => return self.close_form(),
+            Action::Confirm if form.is_valid() => {
+                let setting = &self.config.settings[*index];
+                let key = setting.key.clone();
+                let value = form.fields[0].value.clone();
+                let result = apt_conf::set_value(setting.effective(), &value);
+                self.refresh();
+                self.status = Some(match result {
+                    Ok(()) => StatusMessage::Info(format!("Set {key} to \"{value}\"")),
+                    Err(e) => StatusMessage::Error(e.to_string()),
+                });
+                return self.close_form();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn draw_settings(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.setting_rows.iter().map(|row| match *row {
+            PageRow::Item(index) => {
+                let setting = &self.config.settings[index];
+                let definitions = setting.definitions.len();
+                let note = if setting.is_conflict() {
+                    Cell::from(format!("{definitions} values")).yellow()
+                } else if definitions > 1 && !setting.is_list() {
+                    Cell::from(format!("{definitions} times"))
+                } else {
+                    Cell::from("")
+                };
+                let row = Row::new([
+                    Cell::from(setting.key.clone()),
+                    Cell::from(value_text(setting)),
+                    Cell::from(setting.effective().location()),
+                    note,
+                ]);
+                if setting.is_conflict() {
+                    row.yellow()
+                } else {
+                    row
+                }
+            }
+            PageRow::Problem(index) => problem_row(&self.config.diagnostics[index]),
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(3),
+                Constraint::Fill(3),
+                Constraint::Fill(2),
+                Constraint::Length(9),
+            ],
+        )
+        .header(
+            Row::new(["Key", "Value", "Defined in", "Conflict"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.settings_state);
+    }
+
+    fn draw_unattended(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.source_rows.iter().map(|row| match *row {
+            PageRow::Item(index) => {
+                let source_ref = self.sources[index];
+                let Some((apt_list, source)) = self.repositories.get(source_ref) else {
+                    return Row::default();
+                };
+                let patterns = self.unattended.patterns_of(source_ref);
+                let upgraded = match patterns.first() {
+                    Some(_) if self.unattended.is_enabled() => Cell::from("yes").green(),
+                    Some(_) => Cell::from("if enabled").yellow(),
+                    None => Cell::from("no").dark_gray(),
+                };
+                Row::new([
+                    Cell::from(format!(
+                        "{}:{}",
+                        apt_list.display_name(),
+                        source.line_number
+                    )),
+                    Cell::from(source.masked_uri()),
+                    Cell::from(source.suite.clone()),
+                    upgraded,
+                    Cell::from(
+                        patterns
+                            .first()
+                            .map(|pattern| pattern.definition.value.clone())
+                            .unwrap_or_default(),
+                    ),
+                ])
+            }
+            PageRow::Problem(index) => problem_row(&self.unattended.diagnostics[index]),
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(3),
+                Constraint::Fill(1),
+                Constraint::Length(10),
+                Constraint::Fill(3),
+            ],
+        )
+        .header(
+            Row::new(["Source", "URI", "Suite", "Upgraded", "Matched by"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.sources_state);
+    }
+
+    /// Every definition of the selected setting, the one apt uses last.
+    fn settings_details(&self) -> Vec<Line<'static>> {
+        let Some(row) = self
+            .settings_state
+            .selected()
+            .and_then(|selected| self.setting_rows.get(selected))
+        else {
+            return vec![Line::from("Select a setting to see where it is defined").dark_gray()];
+        };
+        let setting = match *row {
+            PageRow::Item(index) => &self.config.settings[index],
+            PageRow::Problem(index) => {
+                let diagnostic = &self.config.diagnostics[index];
+                return vec![
+                    Line::from(diagnostic.location()).bold(),
+                    severity_line(diagnostic),
+                ];
+            }
+        };
+        let mut lines = vec![Line::from(setting.key.clone()).bold()];
+        let last = setting.definitions.len() - 1;
+        for (index, definition) in setting.definitions.iter().enumerate() {
+            let line = Line::from(format!(
+                "  {:<40} \"{}\"",
+                definition.location(),
+                definition.value
+            ));
+            lines.push(match index == last {
+                _ if setting.is_list() => line,
+                true if setting.is_conflict() => line.green(),
+                true => line,
+                false if definition.value != setting.effective().value => {
+                    line.yellow().add_modifier(Modifier::CROSSED_OUT)
+                }
+                false => line.dark_gray(),
+            });
+        }
+        if setting.is_conflict() {
+            lines.push(
+                Line::from(format!(
+                    "Conflict: apt uses the value of {}, read last (e edits it)",
+                    setting.effective().location()
+                ))
+                .yellow(),
+            );
+        }
+        lines
+    }
+
+    /// Why the selected source is upgraded automatically or not.
+    fn unattended_details(&self) -> Vec<Line<'static>> {
+        let Some(row) = self
+            .sources_state
+            .selected()
+            .and_then(|selected| self.source_rows.get(selected))
+        else {
+            return vec![if self.unattended.patterns.is_empty() {
+                Line::from(format!(
+                    "No {ORIGINS_PATTERN_KEY}, unattended-upgrades upgrades nothing"
+                ))
+                .yellow()
+            } else {
+                Line::from("Select a source to see the patterns that match it").dark_gray()
+            }];
+        };
+        let source_ref = match *row {
+            PageRow::Item(index) => self.sources[index],
+            PageRow::Problem(index) => {
+                let diagnostic = &self.unattended.diagnostics[index];
+                return vec![
+                    Line::from(diagnostic.location()).bold(),
+                    severity_line(diagnostic),
+                ];
+            }
+        };
+        let Some((apt_list, source)) = self.repositories.get(source_ref) else {
+            return Vec::new();
+        };
+        let mut lines = vec![Line::from(format!(
+            "{}:{}",
+            apt_list.file_path.display(),
+            source.line_number
+        ))
+        .bold()];
+        if let Some(target) = self.targets.get(&source_ref) {
+            let fields: Vec<String> = [
+                ("origin", &target.origin),
+                ("label", &target.label),
+                ("archive", &target.archive),
+                ("codename", &target.codename),
+            ]
+            .iter()
+            .filter_map(|(name, value)| Some(format!("{name}={}", value.as_deref()?)))
+            .collect();
+            lines.push(Line::from(format!("Release: {}", fields.join(","))).dark_gray());
+        }
+        let patterns = self.unattended.patterns_of(source_ref);
+        if patterns.is_empty() {
+            lines.push(Line::from("No pattern matches it, it only gets upgrades by hand").yellow());
+        }
+        for pattern in patterns {
+            lines.push(Line::from(format!(
+                "Matched by \"{}\" from {}",
+                pattern.definition.value,
+                pattern.definition.location()
+            )));
+        }
+        lines
+    }
+
+    fn summary(&self) -> String {
+        let mut notes = Vec::new();
+        match self.view {
+            ConfigView::Settings => {
+                notes.push(format!("{} settings", self.config.settings.len()));
+                let conflicts = self.config.conflicts();
+                if conflicts > 0 {
+                    notes.push(format!("{conflicts} conflicts"));
+                }
+                let errors = self
+                    .config
+                    .diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                    .count();
+                if errors > 0 {
+                    notes.push(format!("{errors} files apt refuses"));
+                }
+            }
+            ConfigView::Unattended => {
+                notes.push(match &self.unattended.periodic {
+                    Some(value) if value.trim() == "1" => "runs daily".to_string(),
+                    Some(value) if self.unattended.is_enabled() => {
+                        format!("runs every {value} days")
+                    }
+                    Some(_) => format!("off, {PERIODIC_KEY} is 0"),
+                    None => format!("off, no {PERIODIC_KEY}"),
+                });
+                let upgraded = self
+                    .sources
+                    .iter()
+                    .filter(|source_ref| !self.unattended.patterns_of(**source_ref).is_empty())
+                    .count();
+                notes.push(format!(
+                    "{upgraded} of {} sources upgraded",
+                    self.sources.len()
+                ));
+            }
+        }
+        notes.join(" | ")
+    }
+}
+
+impl Component for AptConfigPage {
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some((form, _)) = self.form.as_mut() else {
+            return Ok(None);
+        };
+        match key.code {
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                form.push_char(c)
+            }
+            KeyCode::Backspace => form.pop_char(),
+            _ => return Ok(None),
+        }
+        self.validate_form();
+        Ok(None)
+    }
+
+    fn update(&mut self, action: Action, view_state: ViewState) -> Result<Option<Action>> {
+        match action {
+            Action::ListAction(list_action) if view_state.mode == Mode::AptConfig => {
+                info!("AptConfigPage handling action: {list_action:?}");
+                self.handle_list_movement(list_action);
+            }
+            Action::ConfigAction(config_action) if view_state.mode == Mode::AptConfig => {
+                return Ok(self.handle_config_action(config_action));
+            }
+            Action::Confirm | Action::Cancel if self.form.is_some() => {
+                return Ok(self.handle_form_action(action));
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(
+        &mut self,
+        view_state: ViewState,
+        frame: &mut Frame,
+        areas: &HashMap<&str, Rect>,
+    ) -> Result<()> {
+        if view_state.page == Page::AptConfig {
+            let area = areas.get("page").unwrap();
+            let border_style = match view_state.mode {
+                Mode::AptConfig => Style::default().fg(Color::Blue),
+                _ => Style::default(),
+            };
+            let block = Block::bordered()
+                .title("Apt Config")
+                .border_style(border_style);
+            let inner = block.inner(*area);
+            frame.render_widget(block, *area);
+
+            if !self.repositories.check_for_repository() {
+                frame.render_widget(Paragraph::new("No apt configuration found"), inner);
+                return Ok(());
+            }
+            if !self.repositories.initialized {
+                self.refresh();
+            }
+
+            let [views_area, table_area, details_area, status_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(8),
+                Constraint::Length(self.status.is_some() as u16),
+            ])
+            .areas(inner);
+            let views = Tabs::new(ConfigView::ALL.map(ConfigView::title))
+                .highlight_style(Style::new().fg(Color::Green).add_modifier(Modifier::BOLD))
+                .select(ConfigView::ALL.iter().position(|view| *view == self.view))
+                .padding(" ", " ");
+            frame.render_widget(views, views_area);
+            frame.render_widget(
+                Paragraph::new(format!("{} ", self.summary()))
+                    .yellow()
+                    .right_aligned(),
+                views_area,
+            );
+            let details = match self.view {
+                ConfigView::Settings => {
+                    self.draw_settings(frame, table_area);
+                    self.settings_details()
+                }
+                ConfigView::Unattended => {
+                    self.draw_unattended(frame, table_area);
+                    self.unattended_details()
+                }
+            };
+            frame.render_widget(
+                Paragraph::new(details)
+                    .block(Block::bordered().borders(Borders::TOP).title("Details"))
+                    .wrap(Wrap { trim: false }),
+                details_area,
+            );
+            match &self.status {
+                Some(StatusMessage::Info(message)) => {
+                    frame.render_widget(Paragraph::new(message.as_str()), status_area)
+                }
+                Some(StatusMessage::Error(message)) => {
+                    frame.render_widget(Paragraph::new(message.as_str()).red(), status_area)
+                }
+                None => {}
+            }
+
+            if let Some((form, _)) = &self.form {
+                form.draw(frame, *area);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// One labelled text input of a `Form`.
+#[derive(Debug, Default, Clone)]
+pub struct FormField {
+    pub label: &'static str,
+    pub value: String,
+    pub error: Option<String>,
+}
+
+/// A popup with text inputs, filled in while the app is in `Mode::TextInput`.
+///
+/// The form only holds the text. Validation belongs to whoever owns the form, which sets the
+/// field errors and the preview after every change.
+#[derive(Debug, Default, Clone)]
+pub struct Form {
+    title: String,
+    pub fields: Vec<FormField>,
+    focused: usize,
+    preview: Vec<Line<'static>>,
+}
+
+impl Form {
+    pub fn new(title: impl Into<String>, fields: Vec<FormField>) -> Self {
+        Self {
+            title: title.into(),
+            fields,
+            focused: 0,
+            preview: Vec::new(),
+        }
+    }
+
+    pub fn next_field(&mut self) {
+        self.focused = (self.focused + 1) % self.fields.len().max(1);
+    }
+
+    pub fn prev_field(&mut self) {
+        self.focused = self
+            .focused
+            .checked_sub(1)
+            .unwrap_or(self.fields.len().saturating_sub(1));
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        if let Some(field) = self.fields.get_mut(self.focused) {
+            field.value.push(c);
+        }
+    }
+
+    pub fn pop_char(&mut self) {
+        if let Some(field) = self.fields.get_mut(self.focused) {
+            field.value.pop();
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.fields.iter().all(|field| field.error.is_none())
+    }
+
+    /// Lines shown under the fields, e.g. the text that will be written.
+    pub fn set_preview(&mut self, preview: Vec<Line<'static>>) {
+        self.preview = preview;
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let [area] = Layout::vertical([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::bordered()
+            .title(self.title.as_str())
+            .title_bottom(" enter: save  tab: next field  esc: cancel ")
+            .border_style(Style::default().fg(Color::Yellow));
+
+        let label_width = self
+            .fields
+            .iter()
+            .map(|field| field.label.len())
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        for (index, field) in self.fields.iter().enumerate() {
+            let focused = index == self.focused;
+            let label_style = if focused {
+                Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD)
+            } else {
+                Style::new()
+            };
+            let mut spans = vec![
+                Span::styled(format!("{:>label_width$}: ", field.label), label_style),
+                Span::raw(field.value.clone()),
+            ];
+            if focused {
+                spans.push(Span::raw("_").add_modifier(Modifier::SLOW_BLINK));
+            }
+            if let Some(error) = &field.error {
+                spans.push(Span::raw(format!("  {error}")).red());
+            }
+            lines.push(Line::from(spans));
+        }
+        if !self.preview.is_empty() {
+            lines.push(Line::default());
+            lines.extend(self.preview.iter().cloned());
+        }
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(block)
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+}
@@ -13,6 +13,7 @@ use crate::ui::{action::Action, Mode, Page, ViewState};
 
 use super::Component;
 
+#[allow(dead_code)]
 #[derive(Default)]
 enum PageFocus {
     #[default]
@@ -34,6 +35,7 @@ impl InstalledPackages {
 }
 
 impl Component for InstalledPackages {
+    #[allow(clippy::single_match, clippy::match_single_binding)]
     fn update(&mut self, action: Action, view_state: ViewState) -> Result<Option<Action>> {
         match action {
             Action::ListAction(list_action) => {
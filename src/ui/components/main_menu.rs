@@ -21,7 +21,8 @@ lazy_static! {
     static ref MENU_OPTIONS: Vec<&'static str> =
         vec!["System",
         // "Installed Packages",
-        "Package Sources"];
+        "Package Sources",
+        "Apt Config"];
 }
 
 #[derive(Default)]
@@ -42,6 +43,7 @@ impl MainMenu {
                 "System" => Page::System,
                 "Installed Packages" => Page::InstalledPackages,
                 "Package Sources" => Page::PackageSources,
+                "Apt Config" => Page::AptConfig,
                 _ => Page::System,
             };
             Some(Action::UpdateViewState(ViewState::new(
@@ -90,6 +92,10 @@ impl MainMenu {
                 Mode::System,
                 view_state.page,
             ))),
+            Page::AptConfig => Some(Action::UpdateViewState(ViewState::new(
+                Mode::AptConfig,
+                view_state.page,
+            ))),
             Page::InstalledPackages => Some(Action::UpdateViewState(ViewState::new(
                 Mode::InstalledPackageTabs,
                 view_state.page,
@@ -119,7 +125,6 @@ impl Component for MainMenu {
                             Ok(None)
                         }
                         ListAction::MakeSelection => Ok(self.focus_page(view_state)),
-                        _ => Ok(None),
                     }
                 } else {
                     Ok(None)
@@ -148,7 +153,7 @@ impl Component for MainMenu {
             )
             .highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
             .highlight_symbol(">");
-        frame.render_stateful_widget(list, area.clone(), &mut self.state);
+        frame.render_stateful_widget(list, *area, &mut self.state);
         Ok(())
     }
 }
@@ -1,50 +1,241 @@
 use std::{
     collections::HashMap,
-    fmt::{self, format},
+    fs,
+    path::{Path, PathBuf},
 };
 
 use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     layout::{Constraint, Flex, Layout, Rect},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, List, ListState, Tabs},
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Tabs, Wrap},
     Frame,
 };
-use tracing::info;
+use tracing::error;
 
 use crate::{
-    repositories::{apt::AptRepositories, Repository},
+    config::{get_data_dir, Config},
+    repositories::{
+        apt::{
+            auth::{self, mask_credentials, AuthEntry, CredentialMove, AUTH_CONF_D_PATH},
+            components::{self, Choice, ComponentEdit},
+            convert::Conversion,
+            dpkg::StatusCache,
+            duplicates::{ConsolidationPlan, DuplicateGroup},
+            fixes::{diff_lines, FileChange, Fix, FixJournal},
+            keyrings::{KeyringStatus, APT_TRUSTED_GPG_D_PATH, LEGACY_TRUSTED_GPG_PATH},
+            legacy_keys::LegacyKeyMigration,
+            lint::Severity,
+            lists::{IndexCache, OrphanedLists, SourceLists, APT_LISTS_PATH},
+            new_source::{NewSource, NewSourceField},
+            openpgp::{self, format_date, KeyExpiry},
+            ppa::Ppa,
+            preferences::DEFAULT_PRIORITY,
+            release::{OsRelease, Release, SuiteIssue},
+            rewrite::CodenameRewrite,
+            vendors::{Vendor, VendorCatalog, VENDORS_FILE},
+            AptRepositories, SourceRef, SourceType,
+        },
+        Repository,
+    },
     ui::{
-        action::{Action, ListAction},
+        action::{Action, ListAction, SourceAction},
         Mode, Page, ViewState,
     },
 };
 
-use super::Component;
+use super::{
+    form::{Form, FormField},
+    popup::Popup,
+    Component,
+};
+
+mod fixes;
+mod keyrings;
+mod lint;
+mod pins;
+
+use fixes::{diff_line, FixView};
+use keyrings::{describe_expiry, KeyringView};
+use lint::LintView;
+use pins::PinView;
+
+/// Fields of the add source form, in the order they are shown.
+const NEW_SOURCE_FIELDS: [(NewSourceField, &str); 6] = [
+    (NewSourceField::Type, "Type"),
+    (NewSourceField::Uri, "URI"),
+    (NewSourceField::Suite, "Suite"),
+    (NewSourceField::Components, "Components"),
+    (NewSourceField::Architectures, "Architectures"),
+    (NewSourceField::SignedBy, "Signed-By"),
+];
 
-#[derive(Debug, Default, PartialEq)]
-enum PageFocus {
+/// The views of the apt tab, switched with `v`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum AptView {
     #[default]
-    Tabs,
-    List,
+    Sources,
+    Keyrings,
+    Pins,
+    Lint,
+    Fixes,
+}
+
+impl AptView {
+    const ALL: [AptView; 5] = [
+        AptView::Sources,
+        AptView::Keyrings,
+        AptView::Pins,
+        AptView::Lint,
+        AptView::Fixes,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Sources => "Sources",
+            Self::Keyrings => "Keyrings",
+            Self::Pins => "Pins",
+            Self::Lint => "Lint",
+            Self::Fixes => "Fixes",
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|view| *view == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Short location of a source, e.g. `vendor.list:3`.
+fn source_location(repositories: &AptRepositories, source_ref: SourceRef) -> String {
+    repositories
+        .get(source_ref)
+        .map(|(apt_list, source)| format!("{}:{}", apt_list.display_name(), source.line_number))
+        .unwrap_or_default()
+}
+
+/// `bytes` in the largest unit that keeps it above 1, e.g. `1.5 MiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Where the applied fixes are recorded, so they can be undone.
+fn fix_journal_path() -> PathBuf {
+    get_data_dir().join("apt-fixes.json")
+}
+
+/// What the form open on this page is for.
+enum FormKind {
+    AddSource,
+    RewriteCodename,
+}
+
+/// Outcome of the last change made from this page, shown under the list.
+enum StatusMessage {
+    Info(String),
+    Error(String),
+}
+
+/// A change waiting for the user to confirm it in a popup.
+enum PendingChange {
+    RemoveFile(PathBuf),
+    Consolidate(ConsolidationPlan),
+    Convert(Vec<Conversion>),
+    DeleteKeyring(PathBuf),
+    MigrateLegacyKeys(LegacyKeyMigration),
+    CleanLists(OrphanedLists),
+    DisableUnused(Vec<SourceRef>),
+    ApplyFixes(Vec<Fix>),
+    UndoFix,
+    MoveCredentials(CredentialMove),
+    /// Nothing to apply, the popup only lists what a source provides.
+    ShowInstalled,
+    /// `cursor` is the file of the plan that space includes or skips.
+    RewriteCodename {
+        plan: CodenameRewrite,
+        cursor: usize,
+    },
+    /// `cursor` is the checkbox that space checks or unchecks.
+    EditComponents {
+        plan: ComponentEdit,
+        cursor: usize,
+    },
 }
 
 #[derive(Default)]
 pub struct PackageSources {
+    config: Config,
     is_enabled: bool,
     repositories: AptRepositories,
     selected_tab: usize,
-    list_state: ListState,
+    view: AptView,
+    table_state: TableState,
+    keyring_view: KeyringView,
+    pin_view: PinView,
+    lint_view: LintView,
+    fix_view: FixView,
+    status: Option<StatusMessage>,
+    popup: Option<(Popup, PendingChange)>,
+    form: Option<(Form, FormKind)>,
+    os_release: OsRelease,
+    duplicates: Vec<DuplicateGroup>,
+    suite_issues: Vec<(SourceRef, SuiteIssue)>,
+    lists: HashMap<SourceRef, SourceLists>,
+    package_counts: HashMap<SourceRef, usize>,
+    index_cache: IndexCache,
+    orphaned_lists: OrphanedLists,
+    installed: StatusCache,
+    /// Installed packages of each enabled `deb` source with a readable index.
+    installed_by_source: HashMap<SourceRef, Vec<String>>,
+    unused_sources: Vec<SourceRef>,
+    auth_entries: Vec<AuthEntry>,
+    vendors: VendorCatalog,
+    /// Whether the catalog in the config directory couldn't be read, the built-in one is used.
+    vendors_unreadable: bool,
 }
 
 impl PackageSources {
     pub fn new() -> Self {
         let repositories = AptRepositories::default();
         Self {
+            config: Config::default(),
             is_enabled: repositories.check_for_repository(),
             repositories,
             selected_tab: 0,
-            list_state: ListState::default(),
+            view: AptView::default(),
+            table_state: TableState::default(),
+            keyring_view: KeyringView::default(),
+            pin_view: PinView::default(),
+            lint_view: LintView::default(),
+            fix_view: FixView::default(),
+            status: None,
+            popup: None,
+            form: None,
+            os_release: OsRelease::default(),
+            duplicates: Vec::new(),
+            suite_issues: Vec::new(),
+            lists: HashMap::new(),
+            package_counts: HashMap::new(),
+            index_cache: IndexCache::default(),
+            orphaned_lists: OrphanedLists::default(),
+            installed: StatusCache::default(),
+            installed_by_source: HashMap::new(),
+            unused_sources: Vec::new(),
+            auth_entries: Vec::new(),
+            vendors: VendorCatalog::builtin(),
+            vendors_unreadable: false,
         }
     }
 
@@ -75,63 +266,1786 @@ impl PackageSources {
     }
 
     fn handle_list_movement(&mut self, list_action: ListAction) -> Result<Option<Action>> {
+        self.status = None;
+        match self.view {
+            AptView::Keyrings => {
+                self.keyring_view.handle_list_movement(list_action);
+                return Ok(None);
+            }
+            AptView::Pins => {
+                self.pin_view.handle_list_movement(list_action);
+                return Ok(None);
+            }
+            AptView::Lint => {
+                self.lint_view.handle_list_movement(list_action);
+                return Ok(None);
+            }
+            AptView::Fixes => {
+                self.fix_view.handle_list_movement(list_action);
+                return Ok(None);
+            }
+            AptView::Sources => {}
+        }
         match list_action {
             ListAction::SelectNext => self.next_list_item(),
             ListAction::SelectPrev => self.prev_list_item(),
             ListAction::SelectFirst => self.first_list_item(),
             ListAction::SelectLast => self.last_list_item(),
             ListAction::SelectNone => self.clear_list_item(),
-            ListAction::MakeSelection => Ok(None), //TODO: implement selection popup
-            _ => Ok(None),
+            ListAction::MakeSelection => self.toggle_selected(),
+        }
+    }
+
+    /// Recomputes everything derived from the loaded sources. Called after every change, so
+    /// the dpkg status is only read again when it changed, and what a source provides is kept
+    /// while its lists and the installed packages stay the same.
+    fn refresh_analysis(&mut self) {
+        self.duplicates = self.repositories.find_duplicates();
+        self.suite_issues = self.repositories.suite_issues(&self.os_release);
+        self.keyring_view.refresh(&self.repositories);
+        self.lint_view.refresh();
+        self.fix_view
+            .refresh(&self.repositories, &self.keyring_view.audit);
+        let installed_changed = self.installed.refresh();
+        let previous_lists = std::mem::replace(&mut self.lists, self.repositories.lists_status());
+        let unchanged = |source_ref: &SourceRef, lists: &SourceLists| {
+            previous_lists.get(source_ref) == Some(lists)
+        };
+
+        let previous_counts = std::mem::take(&mut self.package_counts);
+        self.package_counts = self
+            .lists
+            .iter()
+            .filter_map(|(source_ref, lists)| {
+                let count = match previous_counts.get(source_ref) {
+                    Some(count) if unchanged(source_ref, lists) => Some(*count),
+                    _ => lists.package_count(&mut self.index_cache),
+                };
+                count.map(|count| (*source_ref, count))
+            })
+            .collect();
+
+        let mut previous_provided = std::mem::take(&mut self.installed_by_source);
+        if installed_changed {
+            previous_provided.clear();
+        }
+        let stale: HashMap<SourceRef, SourceLists> = self
+            .lists
+            .iter()
+            .filter(|(source_ref, lists)| {
+                !unchanged(source_ref, lists) || !previous_provided.contains_key(source_ref)
+            })
+            .map(|(source_ref, lists)| (*source_ref, lists.clone()))
+            .collect();
+        self.installed_by_source = self.repositories.installed_by_source(
+            &stale,
+            &mut self.index_cache,
+            self.installed.packages(),
+        );
+        // Kept for the sources whose lists didn't change, as long as they are still enabled
+        self.installed_by_source.extend(
+            previous_provided
+                .into_iter()
+                .filter(|(source_ref, _)| {
+                    self.lists.contains_key(source_ref) && !stale.contains_key(source_ref)
+                })
+                .filter(|(source_ref, _)| {
+                    self.repositories
+                        .get(*source_ref)
+                        .is_some_and(|(_, source)| {
+                            source.enabled && source.source_type == SourceType::Deb
+                        })
+                }),
+        );
+        self.unused_sources = self.repositories.unused_sources(&self.installed_by_source);
+        self.orphaned_lists = self.repositories.orphaned_lists();
+        self.pin_view.refresh(
+            &self.repositories,
+            &self.lists,
+            &mut self.index_cache,
+            self.installed.packages(),
+        );
+    }
+
+    /// Reads the vendor catalog and the auth.conf entries, which only change when the user
+    /// edits them or moves credentials from this page.
+    fn load_settings(&mut self) {
+        self.auth_entries = auth::auth_entries();
+        let vendors_path = self.config.config.config_dir.join(VENDORS_FILE);
+        (self.vendors, self.vendors_unreadable) = match VendorCatalog::load(&vendors_path) {
+            Ok(vendors) => (vendors, false),
+            Err(e) => {
+                error!("{e}");
+                (VendorCatalog::builtin(), true)
+            }
+        };
+    }
+
+    fn selected_ref(&self) -> Option<SourceRef> {
+        self.table_state
+            .selected()
+            .and_then(|selected| self.repositories.ref_of(selected))
+    }
+
+    fn location(&self, source_ref: SourceRef) -> String {
+        source_location(&self.repositories, source_ref)
+    }
+
+    fn open_popup(&mut self, popup: Popup, change: PendingChange) -> Option<Action> {
+        self.popup = Some((popup, change));
+        Some(Action::UpdateViewState(ViewState::new(
+            Mode::ConfirmationPopup,
+            Page::PackageSources,
+        )))
+    }
+
+    fn close_popup(&mut self) -> Option<Action> {
+        self.popup = None;
+        Some(Action::UpdateViewState(ViewState::new(
+            Mode::PackageSourceList,
+            Page::PackageSources,
+        )))
+    }
+
+    fn handle_source_action(&mut self, source_action: SourceAction) -> Result<Option<Action>> {
+        self.status = None;
+        match source_action {
+            SourceAction::NextView => {
+                self.view = self.view.next();
+                Ok(None)
+            }
+            SourceAction::AddSource => Ok(self.open_add_source_form()),
+            SourceAction::RemoveFile if self.view == AptView::Keyrings => {
+                Ok(self.preview_delete_keyring())
+            }
+            SourceAction::RemoveFile
+                if matches!(self.view, AptView::Pins | AptView::Lint | AptView::Fixes) =>
+            {
+                Ok(None)
+            }
+            SourceAction::RemoveFile => self.preview_remove_file(),
+            SourceAction::Consolidate => Ok(self.preview_consolidation()),
+            SourceAction::RewriteCodename => Ok(self.open_rewrite_form()),
+            SourceAction::ConvertFile => Ok(self.preview_convert_file()),
+            SourceAction::ConvertAll => Ok(self.preview_convert_all()),
+            SourceAction::MigrateLegacyKeys => Ok(self.preview_key_migration()),
+            SourceAction::CleanLists => Ok(self.preview_clean_lists()),
+            SourceAction::ShowInstalled => Ok(self.show_installed()),
+            SourceAction::DisableUnused => Ok(self.preview_disable_unused()),
+            SourceAction::ApplyFix => Ok(self.preview_fixes(false)),
+            SourceAction::ApplyAllFixes => Ok(self.preview_fixes(true)),
+            SourceAction::UndoFix => Ok(self.preview_undo_fix()),
+            SourceAction::MoveCredentials => Ok(self.preview_credential_move()),
+            SourceAction::EditComponents => Ok(self.open_component_edit()),
+        }
+    }
+
+    fn preview_credential_move(&mut self) -> Option<Action> {
+        if self.view != AptView::Sources {
+            return None;
+        }
+        let source_ref = self.selected_ref()?;
+        let plan = match self.repositories.plan_credential_move(source_ref) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.status = Some(StatusMessage::Error(e.to_string()));
+                return None;
+            }
+        };
+        let lines = vec![
+            Line::from(format!(
+                "Move the credentials of {} to {}?",
+                mask_credentials(&plan.uri),
+                plan.auth_path.display()
+            ))
+            .bold(),
+            Line::from("Only root can read the file, apt sends them to the matching sources."),
+            Line::default(),
+            Line::from(plan.list_path.display().to_string()).bold(),
+            Line::from(format!("  - {}", mask_credentials(&plan.uri))).red(),
+            Line::from(format!("  + {}", auth::strip_credentials(&plan.uri))).green(),
+            Line::default(),
+            Line::from(plan.auth_path.display().to_string()).bold(),
+            Line::from(format!("  + {}", plan.masked_entry())).green(),
+        ];
+        let popup = Popup::new(" Move credentials ", lines);
+        self.open_popup(popup, PendingChange::MoveCredentials(plan))
+    }
+
+    /// Previews the fix selected in the fixes view, or all of them.
+    fn preview_fixes(&mut self, all: bool) -> Option<Action> {
+        let fixes = if all {
+            self.fix_view.fixes.clone()
+        } else if self.view == AptView::Fixes {
+            self.fix_view.selected_fix().cloned().into_iter().collect()
+        } else {
+            self.status = Some(StatusMessage::Info(
+                "Select a fix in the Fixes view first, F applies all of them".to_string(),
+            ));
+            return None;
+        };
+        if fixes.is_empty() {
+            self.status = Some(StatusMessage::Info("Nothing to fix".to_string()));
+            return None;
+        }
+        let mut lines = vec![
+            Line::from(match fixes.len() {
+                1 => "Apply this fix? z undoes it.".to_string(),
+                count => format!("Apply these {count} fixes? z undoes them."),
+            })
+            .bold(),
+            Line::default(),
+        ];
+        for fix in &fixes {
+            lines.push(Line::from(format!("{}: {fix}", fix.path.display())).bold());
+            match fix.preview() {
+                Ok(diff) => lines.extend(diff.iter().map(diff_line)),
+                Err(e) => lines.push(Line::from(format!("  {e}")).red()),
+            }
+            lines.push(Line::default());
+        }
+        let title = if all {
+            " Apply all fixes "
+        } else {
+            " Apply fix "
+        };
+        self.open_popup(Popup::new(title, lines), PendingChange::ApplyFixes(fixes))
+    }
+
+    fn preview_undo_fix(&mut self) -> Option<Action> {
+        let record = match FixJournal::load(&fix_journal_path()) {
+            Ok(journal) => journal.records.last().cloned(),
+            Err(e) => {
+                self.status = Some(StatusMessage::Error(e.to_string()));
+                return None;
+            }
+        };
+        let Some(record) = record else {
+            self.status = Some(StatusMessage::Info("No fix to undo".to_string()));
+            return None;
+        };
+        let mut lines = vec![
+            Line::from(format!(
+                "Undo the fixes applied on {}?",
+                format_date(record.applied)
+            ))
+            .bold(),
+            Line::default(),
+        ];
+        lines.extend(
+            record
+                .fixes
+                .iter()
+                .map(|fix| Line::from(format!("  {fix}"))),
+        );
+        lines.push(Line::default());
+        for change in &record.changes {
+            lines.push(match change {
+                FileChange::Edit { path, .. } => {
+                    Line::from(format!("  restore {}", path.display()))
+                }
+                FileChange::Rename { from, to } => Line::from(format!(
+                    "  move {} back to {}",
+                    to.display(),
+                    from.display()
+                )),
+            });
+        }
+        self.open_popup(Popup::new(" Undo fix ", lines), PendingChange::UndoFix)
+    }
+
+    fn show_installed(&mut self) -> Option<Action> {
+        if self.view != AptView::Sources {
+            return None;
+        }
+        let source_ref = self.selected_ref()?;
+        let Some(packages) = self.installed_by_source.get(&source_ref) else {
+            self.status = Some(StatusMessage::Info(
+                "Nothing is known about what this source provides".to_string(),
+            ));
+            return None;
+        };
+        let mut lines = vec![Line::from(format!(
+            "{} provides {} of the {} installed packages",
+            self.location(source_ref),
+            packages.len(),
+            self.installed.packages().len()
+        ))
+        .bold()];
+        lines.push(Line::default());
+        lines.extend(
+            packages
+                .iter()
+                .map(|package| Line::from(format!("  {package}"))),
+        );
+        let popup = Popup::new(" Installed packages ", lines).with_footer(" n: close ");
+        self.open_popup(popup, PendingChange::ShowInstalled)
+    }
+
+    fn preview_disable_unused(&mut self) -> Option<Action> {
+        if self.unused_sources.is_empty() {
+            self.status = Some(StatusMessage::Info(
+                "Every enabled source provides an installed package".to_string(),
+            ));
+            return None;
+        }
+        let mut lines = vec![
+            Line::from("Disable these sources? Nothing installed comes from them.").bold(),
+            Line::from("Sources apt has no readable index for are left alone."),
+            Line::default(),
+        ];
+        lines.extend(self.unused_sources.iter().map(|source_ref| {
+            let entry = self
+                .repositories
+                .get(*source_ref)
+                .map(|(_, source)| source.to_string())
+                .unwrap_or_default();
+            Line::from(format!("  {}  {entry}", self.location(*source_ref))).red()
+        }));
+        let popup = Popup::new(" Disable unused sources ", lines);
+        let unused = self.unused_sources.clone();
+        self.open_popup(popup, PendingChange::DisableUnused(unused))
+    }
+
+    fn preview_clean_lists(&mut self) -> Option<Action> {
+        let orphans = self.repositories.orphaned_lists();
+        let kept_for = orphans
+            .kept_for
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if orphans.files.is_empty() {
+            self.status = Some(StatusMessage::Info(if kept_for.is_empty() {
+                format!("Every file in {APT_LISTS_PATH} belongs to an enabled source")
+            } else {
+                format!("No file in {APT_LISTS_PATH} is left to remove, {kept_for} didn't load and apt may still use them")
+            }));
+            return None;
+        }
+        let mut lines = vec![
+            Line::from(format!(
+                "Remove {} files no enabled source uses, {} in total?",
+                orphans.files.len(),
+                format_size(orphans.total_size())
+            ))
+            .bold(),
+            Line::from("apt downloads them again if their source comes back."),
+        ];
+        if !kept_for.is_empty() {
+            lines.push(
+                Line::from(format!(
+                    "Lists under the URIs in {kept_for} are kept: they didn't load, but apt may still read them."
+                ))
+                .yellow(),
+            );
+        }
+        lines.push(Line::default());
+        lines.extend(orphans.files.iter().map(|(path, size)| {
+            Line::from(format!("  {:>10}  {}", format_size(*size), path.display()))
+        }));
+        let popup = Popup::new(" Clean apt lists ", lines);
+        self.open_popup(popup, PendingChange::CleanLists(orphans))
+    }
+
+    fn preview_key_migration(&mut self) -> Option<Action> {
+        if !Path::new(LEGACY_TRUSTED_GPG_PATH).exists() {
+            self.status = Some(StatusMessage::Info(format!(
+                "There is no {LEGACY_TRUSTED_GPG_PATH} to migrate"
+            )));
+            return None;
+        }
+        let plan = match self.repositories.plan_key_migration() {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.status = Some(StatusMessage::Error(e.to_string()));
+                return None;
+            }
+        };
+
+        let mut lines = Vec::new();
+        if plan.moves.is_empty() {
+            lines.push(
+                Line::from(format!(
+                    "No key in {} signs a source without signed-by",
+                    plan.trusted_path.display()
+                ))
+                .bold(),
+            );
+        }
+        for key_move in &plan.moves {
+            lines.push(
+                Line::from(format!(
+                    "{} {} -> {}",
+                    key_move.key.fingerprint,
+                    key_move.key.name(),
+                    key_move.keyring_path.display()
+                ))
+                .bold(),
+            );
+            let mut locations: Vec<String> = key_move
+                .sources
+                .iter()
+                .map(|source_ref| self.location(*source_ref))
+                .collect();
+            locations.dedup();
+            lines.extend(
+                locations
+                    .into_iter()
+                    .map(|location| Line::from(format!("  signs {location}"))),
+            );
+        }
+        lines.push(Line::default());
+        if plan.keeps_trusted() {
+            lines.push(
+                Line::from(format!(
+                    "{} stays as it is, the sources below may need its keys",
+                    plan.trusted_path.display()
+                ))
+                .yellow(),
+            );
+        } else if plan.kept.is_empty() {
+            lines.push(Line::from(format!(
+                "{} is removed, every key has a keyring of its own",
+                plan.trusted_path.display()
+            )));
+        } else {
+            lines.push(
+                Line::from(format!(
+                    "No source found for these, they stay in {}:",
+                    plan.trusted_path.display()
+                ))
+                .bold(),
+            );
+            lines.extend(
+                plan.kept.iter().map(|key| {
+                    Line::from(format!("  {} {}", key.fingerprint, key.name())).yellow()
+                }),
+            );
+        }
+        if !plan.unmatched.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from("Sources without signed-by that can't be matched:").bold());
+            lines.extend(plan.unmatched.iter().map(|(source_ref, reason)| {
+                Line::from(format!("  {}: {reason}", self.location(*source_ref))).yellow()
+            }));
+        }
+        for file in &plan.files {
+            lines.push(Line::default());
+            lines.push(Line::from(file.file_path.display().to_string()).bold());
+            let original: Vec<&str> = file.original.lines().collect();
+            let updated: Vec<&str> = file.updated.lines().collect();
+            lines.extend(
+                original
+                    .iter()
+                    .filter(|line| !updated.contains(line))
+                    .map(|line| Line::from(format!("  - {}", mask_credentials(line))).red()),
+            );
+            lines.extend(
+                updated
+                    .iter()
+                    .filter(|line| !original.contains(line))
+                    .map(|line| Line::from(format!("  + {}", mask_credentials(line))).green()),
+            );
+        }
+        if !plan.moves.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from("Backups:").bold());
+            lines.extend(
+                std::iter::once(&plan.backup_path)
+                    .chain(plan.files.iter().map(|file| &file.backup_path))
+                    .map(|path| Line::from(format!("  {}", path.display()))),
+            );
+        }
+
+        let popup = if plan.moves.is_empty() {
+            Popup::new(" Migrate trusted.gpg ", lines).with_footer(" n: close ")
+        } else {
+            Popup::new(" Migrate trusted.gpg ", lines)
+        };
+        self.open_popup(popup, PendingChange::MigrateLegacyKeys(plan))
+    }
+
+    fn preview_delete_keyring(&mut self) -> Option<Action> {
+        let keyring = self.keyring_view.selected_keyring()?;
+        if keyring.status != KeyringStatus::Orphan {
+            self.status = Some(StatusMessage::Error(format!(
+                "Only orphaned keyrings can be deleted, {} is {}",
+                keyring.path.display(),
+                keyring.status
+            )));
+            return None;
+        }
+        let path = keyring.path.clone();
+        let lines = vec![
+            Line::from(path.display().to_string()).bold(),
+            Line::default(),
+            Line::from("No source uses this keyring and no package owns it."),
+            Line::from("Sources added later with this keyring will fail to verify."),
+        ];
+        let popup = Popup::new(" Delete orphaned keyring ", lines);
+        self.open_popup(popup, PendingChange::DeleteKeyring(path))
+    }
+
+    fn preview_convert_file(&mut self) -> Option<Action> {
+        if self.view != AptView::Sources {
+            return None;
+        }
+        let list_index = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.repositories.list_index_of(selected))?;
+        match self.repositories.plan_conversion(list_index) {
+            Ok(conversion) => self.preview_conversions(vec![conversion], Vec::new()),
+            Err(e) => {
+                self.status = Some(StatusMessage::Error(e.to_string()));
+                None
+            }
+        }
+    }
+
+    fn preview_convert_all(&mut self) -> Option<Action> {
+        let (conversions, failures) = self.repositories.plan_all_conversions();
+        if conversions.is_empty() && failures.is_empty() {
+            self.status = Some(StatusMessage::Info(
+                "Every source file is in deb822 format already".to_string(),
+            ));
+            return None;
+        }
+        self.preview_conversions(conversions, failures)
+    }
+
+    /// Shows the deb822 text of every file that is about to be converted.
+    fn preview_conversions(
+        &mut self,
+        conversions: Vec<Conversion>,
+        failures: Vec<(PathBuf, String)>,
+    ) -> Option<Action> {
+        let mut lines = Vec::new();
+        for conversion in &conversions {
+            lines.push(
+                Line::from(format!(
+                    "{} -> {}",
+                    conversion.list_path.display(),
+                    conversion.target_path.display()
+                ))
+                .bold(),
+            );
+            lines.push(Line::from(format!(
+                "  the original is kept as {}",
+                conversion.backup_path.display()
+            )));
+            lines.extend(
+                conversion
+                    .content
+                    .lines()
+                    .map(|line| Line::from(format!("    {}", mask_credentials(line))).green()),
+            );
+            lines.push(Line::default());
+        }
+        for (path, error) in &failures {
+            lines.push(Line::from(format!("{} can't be converted: {error}", path.display())).red());
+        }
+        let popup = if conversions.is_empty() {
+            Popup::new(" Convert to deb822 ", lines).with_footer(" n: close ")
+        } else {
+            Popup::new(" Convert to deb822 ", lines)
+        };
+        self.open_popup(popup, PendingChange::Convert(conversions))
+    }
+
+    fn preview_consolidation(&mut self) -> Option<Action> {
+        let plan = self.repositories.plan_consolidation();
+        if plan.disable.is_empty() && plan.skipped.is_empty() {
+            self.status = Some(StatusMessage::Info("No duplicate sources".to_string()));
+            return None;
+        }
+
+        let describe = |source_ref: SourceRef| {
+            let entry = self
+                .repositories
+                .get(source_ref)
+                .map(|(_, source)| source.to_string())
+                .unwrap_or_default();
+            format!("  {}  {entry}", self.location(source_ref))
+        };
+        let mut lines = vec![Line::from("Keep:").bold()];
+        lines.extend(
+            plan.keep
+                .iter()
+                .map(|source_ref| Line::from(describe(*source_ref))),
+        );
+        lines.push(Line::default());
+        lines.push(Line::from("Disable:").bold());
+        if plan.disable.is_empty() {
+            lines.push(Line::from(
+                "  nothing can be disabled without losing components",
+            ));
+        }
+        lines.extend(
+            plan.disable
+                .iter()
+                .map(|source_ref| Line::from(describe(*source_ref)).red()),
+        );
+        if !plan.skipped.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from("Needs a manual merge:").bold());
+            lines.extend(plan.skipped.iter().map(|(source_ref, reason)| {
+                Line::from(format!("{} ({reason})", describe(*source_ref))).yellow()
+            }));
+        }
+
+        let popup = Popup::new(" Consolidate duplicate sources ", lines);
+        self.open_popup(popup, PendingChange::Consolidate(plan))
+    }
+
+    fn open_add_source_form(&mut self) -> Option<Action> {
+        let defaults = NewSource::default();
+        let fields = NEW_SOURCE_FIELDS
+            .iter()
+            .map(|(field, label)| FormField {
+                label,
+                value: defaults.get(*field).to_string(),
+                error: None,
+            })
+            .collect();
+        self.open_form(Form::new(" Add source ", fields), FormKind::AddSource)
+    }
+
+    fn open_form(&mut self, form: Form, kind: FormKind) -> Option<Action> {
+        self.form = Some((form, kind));
+        self.validate_form();
+        Some(Action::UpdateViewState(ViewState::new(
+            Mode::TextInput,
+            Page::PackageSources,
+        )))
+    }
+
+    fn validate_form(&mut self) {
+        match self.form.as_mut() {
+            Some((form, FormKind::AddSource)) => {
+                Self::validate_new_source(form, &self.config, &self.os_release, &self.repositories)
+            }
+            Some((form, FormKind::RewriteCodename)) => {
+                Self::validate_rewrite(form, &self.repositories)
+            }
+            None => {}
+        }
+    }
+
+    /// Suggests upgrading from the running release to the next one.
+    fn open_rewrite_form(&mut self) -> Option<Action> {
+        let from = self.os_release.codename().unwrap_or_default().to_string();
+        let to = Release::from_codename(&from)
+            .and_then(|release| release.successor())
+            .map(|release| release.codename)
+            .unwrap_or_default();
+        let fields = vec![
+            FormField {
+                label: "From",
+                value: from,
+                error: None,
+            },
+            FormField {
+                label: "To",
+                value: to,
+                error: None,
+            },
+        ];
+        self.open_form(
+            Form::new(" Rewrite codename ", fields),
+            FormKind::RewriteCodename,
+        )
+    }
+
+    fn validate_rewrite(form: &mut Form, repositories: &AptRepositories) {
+        let codename_error = |value: &str| {
+            if value.trim().is_empty() {
+                Some("required".to_string())
+            } else if value.trim().contains(char::is_whitespace) {
+                Some("one codename, e.g. bookworm".to_string())
+            } else {
+                None
+            }
+        };
+        let from = form.fields[0].value.trim().to_string();
+        let to = form.fields[1].value.trim().to_string();
+        form.fields[0].error = codename_error(&from);
+        form.fields[1].error = codename_error(&to)
+            .or_else(|| (from == to).then(|| "must differ from the current codename".to_string()));
+
+        let count = repositories.count_codename_uses(&from);
+        let preview = if from.is_empty() {
+            Vec::new()
+        } else if count == 0 {
+            vec![Line::from(format!("No source uses {from}")).yellow()]
+        } else {
+            vec![Line::from(format!(
+                "Renames the suites of {count} sources, including -updates, -security and -backports"
+            ))]
+        };
+        form.set_preview(preview);
+    }
+
+    /// Makes the plan for the rewrite form and shows it, file by file.
+    fn preview_rewrite(&mut self, from: &str, to: &str) -> Option<Action> {
+        let plan = match self.repositories.plan_codename_rewrite(from, to) {
+            Ok(plan) if plan.files.is_empty() => {
+                self.status = Some(StatusMessage::Info(format!("No source uses {from}")));
+                return self.close_popup();
+            }
+            Ok(plan) => plan,
+            Err(e) => {
+                self.status = Some(StatusMessage::Error(e.to_string()));
+                return self.close_popup();
+            }
+        };
+        let popup = Popup::new(
+            format!(" Rewrite {from} to {to} "),
+            Self::rewrite_lines(&plan, 0).0,
+        )
+        .with_footer(" y: write  space: skip or include file  tab: next file  n: cancel ");
+        self.open_popup(popup, PendingChange::RewriteCodename { plan, cursor: 0 })
+    }
+
+    /// The preview of a rewrite, and the line the file at `cursor` starts on.
+    fn rewrite_lines(plan: &CodenameRewrite, cursor: usize) -> (Vec<Line<'static>>, usize) {
+        let mut lines = Vec::new();
+        let mut cursor_line = 0;
+        for (index, file) in plan.files.iter().enumerate() {
+            if index == cursor {
+                cursor_line = lines.len();
+            }
+            let marker = if index == cursor { ">" } else { " " };
+            let checkbox = if file.skip { "[ ]" } else { "[x]" };
+            let header =
+                Line::from(format!("{marker} {checkbox} {}", file.file_path.display())).bold();
+            lines.push(if file.skip {
+                header.dark_gray()
+            } else {
+                header
+            });
+            if !file.unknown_targets.is_empty() {
+                lines.push(
+                    Line::from(format!(
+                        "      may not publish {}: {}",
+                        plan.to,
+                        file.unknown_targets.join(", ")
+                    ))
+                    .yellow(),
+                );
+            }
+            for (line_number, old, new) in file.diff() {
+                let (old, new) = (mask_credentials(old), mask_credentials(new));
+                lines.push(Line::from(format!("    {line_number:>4} - {old}")).red());
+                lines.push(Line::from(format!("    {line_number:>4} + {new}")).green());
+            }
+            lines.push(Line::default());
+        }
+        (lines, cursor_line)
+    }
+
+    /// Moves the file cursor of a rewrite preview, or includes or skips the file under it.
+    fn update_rewrite_preview(&mut self, action: Action) {
+        let Some((popup, PendingChange::RewriteCodename { plan, cursor })) = self.popup.as_mut()
+        else {
+            return;
+        };
+        let file_count = plan.files.len().max(1);
+        match action {
+            Action::NextField => *cursor = (*cursor + 1) % file_count,
+            Action::PrevField => *cursor = cursor.checked_sub(1).unwrap_or(file_count - 1),
+            Action::ListAction(ListAction::MakeSelection) => {
+                if let Some(file) = plan.files.get_mut(*cursor) {
+                    file.skip = !file.skip;
+                }
+            }
+            _ => return,
+        }
+        let (lines, cursor_line) = Self::rewrite_lines(plan, *cursor);
+        popup.set_lines(lines);
+        if !matches!(action, Action::ListAction(_)) {
+            popup.scroll_to(cursor_line);
         }
     }
 
+    /// Opens the checkboxes for the components and architectures of the selected source.
+    fn open_component_edit(&mut self) -> Option<Action> {
+        if self.view != AptView::Sources {
+            return None;
+        }
+        let source_ref = self.selected_ref()?;
+        let release = self
+            .lists
+            .get(&source_ref)
+            .and_then(|lists| lists.release_path.as_ref())
+            .and_then(|path| fs::read_to_string(path).ok());
+        let architectures = components::dpkg_architectures(self.installed.packages());
+        let plan = match self.repositories.plan_component_edit(
+            source_ref,
+            release.as_deref(),
+            &architectures,
+        ) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.status = Some(StatusMessage::Error(e.to_string()));
+                return None;
+            }
+        };
+        let popup = Popup::new(
+            format!(" Components of {} ", self.location(source_ref)),
+            Self::component_lines(&self.repositories, &plan, 0),
+        )
+        .with_footer(" y: write  space: check or uncheck  tab: next  n: cancel ");
+        self.open_popup(popup, PendingChange::EditComponents { plan, cursor: 0 })
+    }
+
+    /// The checkboxes of a component edit, followed by the change to the file.
+    fn component_lines(
+        repositories: &AptRepositories,
+        plan: &ComponentEdit,
+        cursor: usize,
+    ) -> Vec<Line<'static>> {
+        let checkbox = |index: usize, choice: &Choice| {
+            let marker = if index == cursor { ">" } else { " " };
+            let mark = if choice.checked { "[x]" } else { "[ ]" };
+            let line = Line::from(format!("{marker} {mark} {}", choice.name));
+            if choice.checked {
+                line
+            } else {
+                line.dark_gray()
+            }
+        };
+        let mut lines = Vec::new();
+        if plan.components.is_empty() {
+            lines.push(Line::from("The suite is an exact path, it has no components").dark_gray());
+        } else {
+            lines.push(Line::from("Components").bold());
+            lines.extend(
+                plan.components
+                    .iter()
+                    .enumerate()
+                    .map(|(index, choice)| checkbox(index, choice)),
+            );
+        }
+        lines.push(Line::default());
+        lines.push(Line::from("Architectures, all checked adds no arch option").bold());
+        lines.extend(
+            plan.architectures
+                .iter()
+                .enumerate()
+                .map(|(index, choice)| checkbox(plan.components.len() + index, choice)),
+        );
+        lines.push(Line::default());
+        if !plan.shared_with.is_empty() {
+            let shared: Vec<String> = plan
+                .shared_with
+                .iter()
+                .filter_map(|source_ref| repositories.get(*source_ref))
+                .map(|(_, source)| format!("{} {}", source.source_type, source.suite))
+                .collect();
+            lines.push(
+                Line::from(format!(
+                    "The stanza also holds {}, they change too",
+                    shared.join(", ")
+                ))
+                .yellow(),
+            );
+        }
+        if let Some(problem) = plan.problem() {
+            lines.push(Line::from(format!("Can't write the source: {problem}")).red());
+            return lines;
+        }
+        match repositories.preview_component_edit(plan) {
+            Ok((content, updated)) if content == updated => {
+                lines.push(Line::from("No change").dark_gray())
+            }
+            Ok((content, updated)) => {
+                lines.extend(diff_lines(&content, &updated).iter().map(diff_line))
+            }
+            Err(e) => lines.push(Line::from(e.to_string()).red()),
+        }
+        lines
+    }
+
+    /// Moves the checkbox cursor of a component edit, or checks or unchecks the box under it.
+    fn update_component_edit(&mut self, action: Action) {
+        let Some((popup, PendingChange::EditComponents { plan, cursor })) = self.popup.as_mut()
+        else {
+            return;
+        };
+        let count = plan.len().max(1);
+        match action {
+            Action::NextField => *cursor = (*cursor + 1) % count,
+            Action::PrevField => *cursor = cursor.checked_sub(1).unwrap_or(count - 1),
+            Action::ListAction(ListAction::MakeSelection) => plan.toggle(*cursor),
+            _ => return,
+        }
+        popup.set_lines(Self::component_lines(&self.repositories, plan, *cursor));
+    }
+
+    /// Passes the cursor keys of a popup to the preview that has a cursor.
+    fn update_popup_cursor(&mut self, action: Action) {
+        match self.popup {
+            Some((_, PendingChange::EditComponents { .. })) => self.update_component_edit(action),
+            _ => self.update_rewrite_preview(action),
+        }
+    }
+
+    /// The source the form describes, with a `ppa:owner/name` URI expanded for the running
+    /// release.
+    fn new_source_from_form(form: &Form, os_release: &OsRelease) -> NewSource {
+        let mut source = NewSource::default();
+        for ((field, _), form_field) in NEW_SOURCE_FIELDS.iter().zip(&form.fields) {
+            *source.get_mut(*field) = form_field.value.clone();
+        }
+        source.expand_ppa(os_release.codename())
+    }
+
+    /// The file that already has a source for the PPA of `source`, which the form refuses to
+    /// add again.
+    fn existing_ppa_list(repositories: &AptRepositories, source: &NewSource) -> Option<usize> {
+        let ppa = source.ppa.as_ref()?;
+        repositories
+            .ppa_sources()
+            .into_iter()
+            .find(|(_, existing)| existing == ppa)
+            .map(|(source_ref, _)| source_ref.list)
+    }
+
+    /// The PPAs already configured, listed under the add source form.
+    fn ppa_lines(repositories: &AptRepositories) -> Vec<Line<'static>> {
+        let mut ppas: Vec<(Ppa, String)> = repositories
+            .ppa_sources()
+            .into_iter()
+            .map(|(source_ref, ppa)| (ppa, source_location(repositories, source_ref)))
+            .collect();
+        ppas.dedup_by(|a, b| a.0 == b.0);
+        if ppas.is_empty() {
+            return Vec::new();
+        }
+        let mut lines = vec![
+            Line::default(),
+            Line::from("PPAs already added, select one in the list and press d to remove it:")
+                .bold(),
+        ];
+        lines.extend(
+            ppas.into_iter()
+                .map(|(ppa, location)| Line::from(format!("  {ppa}  {location}"))),
+        );
+        lines
+    }
+
+    /// Refreshes the field errors and the file preview after every change to the form.
+    fn validate_new_source(
+        form: &mut Form,
+        config: &Config,
+        os_release: &OsRelease,
+        repositories: &AptRepositories,
+    ) {
+        let source = Self::new_source_from_form(form, os_release);
+        let mut errors = source.validate();
+        if let Some(list_index) = Self::existing_ppa_list(repositories, &source) {
+            errors.push((
+                NewSourceField::Uri,
+                format!(
+                    "already configured in {}",
+                    repositories.items[list_index].display_name()
+                ),
+            ));
+        }
+        for ((field, _), form_field) in NEW_SOURCE_FIELDS.iter().zip(form.fields.iter_mut()) {
+            form_field.error = errors
+                .iter()
+                .find(|(error_field, _)| error_field == field)
+                .map(|(_, error)| error.clone());
+        }
+
+        let format = config.apt.new_source_format;
+        let mut preview =
+            vec![Line::from(format!("Will be written to {}", source.file_name(format))).bold()];
+        preview.extend(
+            source
+                .render(format)
+                .lines()
+                .map(|line| Line::from(format!("  {line}")).dark_gray()),
+        );
+        if let Some(keyring) = source.keyring_destination() {
+            preview.push(Line::from(format!(
+                "Copies the key {} to {}",
+                source.signed_by.trim(),
+                keyring.display()
+            )));
+        }
+        if source.ppa.is_some() && os_release.id != "ubuntu" {
+            preview.push(
+                Line::from(format!(
+                    "PPAs are built for Ubuntu releases, {} may not be able to install from it",
+                    os_release.pretty_name
+                ))
+                .yellow(),
+            );
+        }
+        preview.extend(Self::ppa_lines(repositories));
+        form.set_preview(preview);
+    }
+
+    fn handle_form_action(&mut self, action: Action) -> Result<Option<Action>> {
+        let Some((form, kind)) = self.form.as_mut() else {
+            return Ok(None);
+        };
+        match action {
+            Action::NextField => form.next_field(),
+            Action::PrevField => form.prev_field(),
+            Action::Cancel => {
+                self.form = None;
+                return Ok(self.close_popup());
+            }
+            Action::Confirm if !form.is_valid() => {}
+            Action::Confirm if matches!(kind, FormKind::RewriteCodename) => {
+                let from = form.fields[0].value.trim().to_string();
+                let to = form.fields[1].value.trim().to_string();
+                self.form = None;
+                return Ok(self.preview_rewrite(&from, &to));
+            }
+            Action::Confirm => {
+                let source = Self::new_source_from_form(form, &self.os_release);
+                self.form = None;
+                self.status = Some(
+                    match self
+                        .repositories
+                        .add_source(&source, self.config.apt.new_source_format)
+                    {
+                        Ok(path) => StatusMessage::Info(format!("Added {}", path.display())),
+                        Err(e) => StatusMessage::Error(e.to_string()),
+                    },
+                );
+                self.refresh_analysis();
+                return Ok(self.close_popup());
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_popup_movement(&mut self, list_action: ListAction) -> Result<Option<Action>> {
+        if let Some((popup, _)) = self.popup.as_mut() {
+            match list_action {
+                ListAction::SelectNext => popup.scroll_down(),
+                ListAction::SelectPrev => popup.scroll_up(),
+                ListAction::MakeSelection => {
+                    self.update_popup_cursor(Action::ListAction(list_action))
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    fn confirm_popup(&mut self) -> Result<Option<Action>> {
+        let Some((_, change)) = self.popup.take() else {
+            return Ok(None);
+        };
+        let result = match change {
+            PendingChange::RemoveFile(path) => self
+                .repositories
+                .remove_list(&path)
+                .map(|_| format!("Removed {}", path.display())),
+            PendingChange::Consolidate(plan) => self
+                .repositories
+                .apply_consolidation(&plan)
+                .map(|count| format!("Disabled {count} duplicate sources")),
+            PendingChange::Convert(conversions) if conversions.is_empty() => {
+                return Ok(self.close_popup());
+            }
+            PendingChange::Convert(conversions) => self
+                .repositories
+                .apply_conversions(&conversions)
+                .map(|count| format!("Converted {count} files to deb822")),
+            PendingChange::MigrateLegacyKeys(plan) if plan.moves.is_empty() => {
+                return Ok(self.close_popup());
+            }
+            PendingChange::MigrateLegacyKeys(plan) => {
+                self.repositories.apply_key_migration(&plan).map(|count| {
+                    let verb = if plan.keeps_trusted() {
+                        "Copied"
+                    } else {
+                        "Moved"
+                    };
+                    format!("{verb} {count} keys to keyrings of their own")
+                })
+            }
+            PendingChange::CleanLists(orphans) => self
+                .repositories
+                .remove_orphaned_lists(&orphans)
+                .map(|(count, size)| format!("Removed {count} files, {}", format_size(size))),
+            PendingChange::DisableUnused(source_refs) => self
+                .repositories
+                .disable_sources(&source_refs)
+                .map(|count| format!("Disabled {count} unused sources")),
+            PendingChange::ShowInstalled => return Ok(self.close_popup()),
+            PendingChange::ApplyFixes(fixes) => self
+                .repositories
+                .apply_fixes(&fixes, &fix_journal_path())
+                .map(|count| format!("Applied {count} fixes, z undoes them")),
+            PendingChange::UndoFix => self
+                .repositories
+                .undo_last_fix(&fix_journal_path())
+                .map(|fixes| format!("Undid {} fixes", fixes.len())),
+            PendingChange::MoveCredentials(plan) => {
+                let result = self
+                    .repositories
+                    .apply_credential_move(&plan)
+                    .map(|path| format!("Moved the credentials to {}", path.display()));
+                self.load_settings();
+                result
+            }
+            PendingChange::DeleteKeyring(path) => self
+                .repositories
+                .delete_keyring(&path)
+                .map(|_| format!("Deleted {}", path.display())),
+            PendingChange::RewriteCodename { plan, .. } => self
+                .repositories
+                .apply_codename_rewrite(&plan)
+                .map(|count| format!("Rewrote {} to {} in {count} files", plan.from, plan.to)),
+            PendingChange::EditComponents { plan, .. } => {
+                let location = self.location(plan.source_ref);
+                self.repositories
+                    .apply_component_edit(&plan)
+                    .map(|changed| {
+                        if changed {
+                            format!("Updated {location}")
+                        } else {
+                            "No change".to_string()
+                        }
+                    })
+            }
+        };
+        self.refresh_analysis();
+        self.status = Some(match result {
+            Ok(message) => StatusMessage::Info(message),
+            Err(e) => StatusMessage::Error(e.to_string()),
+        });
+        let selected_count = self.repositories.get_repository_list().len();
+        if self
+            .table_state
+            .selected()
+            .is_some_and(|selected| selected >= selected_count)
+        {
+            self.table_state.select(selected_count.checked_sub(1));
+        }
+        Ok(self.close_popup())
+    }
+
+    fn preview_remove_file(&mut self) -> Result<Option<Action>> {
+        match self
+            .table_state
+            .selected()
+            .and_then(|selected| self.repositories.list_index_of(selected))
+        {
+            Some(list_index) => self.preview_remove_list(list_index),
+            None => Ok(None),
+        }
+    }
+
+    fn preview_remove_list(&mut self, list_index: usize) -> Result<Option<Action>> {
+        let preview = match self.repositories.preview_removal(list_index) {
+            Ok(preview) => preview,
+            Err(e) => {
+                self.status = Some(StatusMessage::Error(e.to_string()));
+                return Ok(None);
+            }
+        };
+
+        let mut lines = vec![
+            Line::from(format!("Remove {}?", preview.file_path.display())).bold(),
+            Line::default(),
+            Line::from("Entries:").bold(),
+        ];
+        lines.extend(
+            preview
+                .entries
+                .iter()
+                .map(|entry| Line::from(format!("  {entry}"))),
+        );
+        lines.push(Line::default());
+        if preview.unused_keyrings.is_empty() {
+            lines.push(Line::from("No keyring becomes unused"));
+        } else {
+            lines.push(Line::from("Keyrings no other source uses:").bold());
+            lines.extend(
+                preview
+                    .unused_keyrings
+                    .iter()
+                    .map(|keyring| Line::from(format!("  {keyring}")).yellow()),
+            );
+        }
+        lines.push(Line::default());
+        lines.push(Line::from("File content:").bold());
+        lines.extend(
+            preview
+                .content
+                .lines()
+                .map(|line| Line::from(format!("  {}", mask_credentials(line))).dark_gray()),
+        );
+
+        let popup = Popup::new(" Remove source file ", lines);
+        Ok(self.open_popup(popup, PendingChange::RemoveFile(preview.file_path)))
+    }
+
+    fn toggle_selected(&mut self) -> Result<Option<Action>> {
+        let Some(selected) = self.table_state.selected() else {
+            return Ok(None);
+        };
+        self.status = Some(match self.repositories.toggle_source(selected) {
+            Ok(true) => StatusMessage::Info("Source enabled".to_string()),
+            Ok(false) => StatusMessage::Info("Source disabled".to_string()),
+            Err(e) => StatusMessage::Error(e.to_string()),
+        });
+        self.refresh_analysis();
+        Ok(None)
+    }
+
     fn clear_list_item(&mut self) -> Result<Option<Action>> {
-        self.list_state.select(None);
+        self.table_state.select(None);
         Ok(None)
     }
 
     fn next_list_item(&mut self) -> Result<Option<Action>> {
-        let selected = self.list_state.selected().unwrap_or(0);
-        if selected < self.repositories.get_repository_list().len() - 1 {
-            self.list_state.select_next();
+        let selected = self.table_state.selected().unwrap_or(0);
+        if selected
+            < self
+                .repositories
+                .get_repository_list()
+                .len()
+                .saturating_sub(1)
+        {
+            self.table_state.select_next();
         } else {
-            self.list_state.select_first();
+            self.table_state.select_first();
         }
         Ok(None)
     }
 
     fn prev_list_item(&mut self) -> Result<Option<Action>> {
-        let selected = self.list_state.selected().unwrap_or(0);
+        let selected = self.table_state.selected().unwrap_or(0);
         if selected > 0 {
-            self.list_state.select_previous();
+            self.table_state.select_previous();
         } else {
-            self.list_state
-                .select(Some(self.repositories.get_repository_list().len() - 1));
+            self.table_state.select(Some(
+                self.repositories
+                    .get_repository_list()
+                    .len()
+                    .saturating_sub(1),
+            ));
         }
         Ok(None)
     }
 
     fn first_list_item(&mut self) -> Result<Option<Action>> {
-        self.list_state.select_first();
+        self.table_state.select_first();
         Ok(None)
     }
 
     fn last_list_item(&mut self) -> Result<Option<Action>> {
-        self.list_state
-            .select(Some(self.repositories.get_repository_list().len() - 1));
+        self.table_state.select(Some(
+            self.repositories
+                .get_repository_list()
+                .len()
+                .saturating_sub(1),
+        ));
         Ok(None)
     }
 }
 
+impl PackageSources {
+    /// Short markers for problems with a source, shown in the list.
+    fn badges(&self, source_ref: SourceRef) -> Vec<Span<'static>> {
+        let mut badges = Vec::new();
+        if self
+            .duplicates
+            .iter()
+            .any(|group| group.sources.contains(&source_ref))
+        {
+            badges.push(Span::raw("dup").yellow());
+        }
+        for (_, issue) in self
+            .suite_issues
+            .iter()
+            .filter(|(issue_ref, _)| *issue_ref == source_ref)
+        {
+            badges.push(match issue {
+                SuiteIssue::Mixed { .. } => Span::raw(issue.badge()).red(),
+                _ => Span::raw(issue.badge()).yellow(),
+            });
+        }
+        if let Some(keyring) = self.keyring_view.audit.keyring_of(source_ref) {
+            match keyring.expiry(openpgp::now()) {
+                _ if keyring.status == KeyringStatus::Missing => {
+                    badges.push(Span::raw("nokey").red())
+                }
+                Some(KeyExpiry::Revoked | KeyExpiry::Expired(_)) => {
+                    badges.push(Span::raw("keyexp").red())
+                }
+                Some(KeyExpiry::Soon(_)) => badges.push(Span::raw("keysoon").yellow()),
+                _ => {}
+            }
+        }
+        if let Some((_, Some(false))) = self.vendor_of(source_ref) {
+            badges.push(Span::raw("vendorkey").red());
+        }
+        if let Some(lists) = self
+            .lists
+            .get(&source_ref)
+            .filter(|_| self.is_source_enabled(source_ref))
+        {
+            if lists.is_unfetched() {
+                badges.push(Span::raw("noidx").red());
+            } else if lists.is_expired(openpgp::now()) {
+                badges.push(Span::raw("stale").red());
+            }
+        }
+        if let Some((apt_list, source)) = self.repositories.get(source_ref) {
+            let worst = self
+                .lint_view
+                .diagnostics_of(&apt_list.file_path, source)
+                .map(|diagnostic| diagnostic.severity)
+                .min();
+            match worst {
+                Some(Severity::Error) => badges.push(Span::raw("lint").red()),
+                Some(Severity::Warning) => badges.push(Span::raw("lint").yellow()),
+                Some(Severity::Info) | None => {}
+            }
+        }
+        if self.unused_sources.contains(&source_ref)
+            && self.installed_by_source.contains_key(&source_ref)
+        {
+            badges.push(Span::raw("unused").yellow());
+        }
+        if self
+            .repositories
+            .get(source_ref)
+            .is_some_and(|(_, source)| source.has_credentials())
+        {
+            badges.push(Span::raw("creds").red());
+        }
+        if let Some((priority, _)) = self
+            .pin_view
+            .priority_of(source_ref)
+            .filter(|(priority, _)| *priority != DEFAULT_PRIORITY)
+        {
+            let badge = Span::raw(format!("pin{priority}"));
+            badges.push(if priority < 0 { badge.red() } else { badge });
+        }
+
+        let mut spaced = Vec::new();
+        for badge in badges {
+            if !spaced.is_empty() {
+                spaced.push(Span::raw(" "));
+            }
+            spaced.push(badge);
+        }
+        spaced
+    }
+
+    /// The catalog entry for a source, and whether apt accepts the key the catalog expects for it.
+    fn vendor_of(&self, source_ref: SourceRef) -> Option<(&Vendor, Option<bool>)> {
+        let (_, source) = self.repositories.get(source_ref)?;
+        let vendor = self.vendors.vendor_of(&source.uri)?;
+        let expected_key =
+            vendor.is_signed_by_expected_key(source_ref, source, &self.keyring_view.audit);
+        Some((vendor, expected_key))
+    }
+
+    fn is_source_enabled(&self, source_ref: SourceRef) -> bool {
+        self.repositories
+            .get(source_ref)
+            .is_some_and(|(_, source)| source.enabled)
+    }
+
+    /// When apt last fetched the source, and what it got.
+    fn lists_details(&self, source_ref: SourceRef) -> Vec<Line<'static>> {
+        let Some(lists) = self.lists.get(&source_ref) else {
+            return Vec::new();
+        };
+        if lists.is_unfetched() {
+            return vec![Line::from(format!(
+                "Fetched: never, nothing for it in {APT_LISTS_PATH}"
+            ))
+            .red()];
+        }
+        let now = openpgp::now();
+        let mut fetched = vec![format!(
+            "Fetched: {}",
+            lists.fetched.map_or("unknown".to_string(), format_date)
+        )];
+        if let Some(date) = lists.date {
+            fetched.push(format!(
+                "published {} ({} days ago)",
+                format_date(date),
+                now.saturating_sub(date) / 86_400
+            ));
+        }
+        if let Some(valid_until) = lists.valid_until {
+            fetched.push(format!("valid until {}", format_date(valid_until)));
+        }
+        let fetched = Line::from(fetched.join(", "));
+        let mut lines = vec![if lists.is_expired(now) {
+            fetched.red()
+        } else {
+            fetched
+        }];
+        let indexes = match lists.indexes.len() {
+            1 => "1 index".to_string(),
+            count => format!("{count} indexes"),
+        };
+        let packages = match self.package_counts.get(&source_ref) {
+            Some(count) => format!("Packages: {count} in {indexes}"),
+            None => format!("Packages: unknown, can't read {indexes}"),
+        };
+        let packages = match self.installed_by_source.get(&source_ref) {
+            Some(installed) if installed.is_empty() => {
+                format!("{packages}, none installed (U disables unused sources)")
+            }
+            Some(installed) => format!(
+                "{packages}, provides {} installed (p lists them)",
+                installed.len()
+            ),
+            None => packages,
+        };
+        if lists.missing.is_empty() {
+            lines.push(Line::from(packages));
+        } else {
+            lines.push(
+                Line::from(format!(
+                    "{packages}, no index for {}",
+                    lists.missing.join(" ")
+                ))
+                .yellow(),
+            );
+        }
+        lines
+    }
+
+    /// The priority of the source and the pins that select it.
+    fn pin_details(&self, source_ref: SourceRef) -> Vec<Line<'static>> {
+        let Some((priority, by)) = self.pin_view.priority_of(source_ref) else {
+            return Vec::new();
+        };
+        let mut lines = Vec::new();
+        match by {
+            Some(stanza) if priority < 0 => lines.push(
+                Line::from(format!(
+                    "Priority: {priority} from {}, apt installs nothing from it as if it were disabled",
+                    stanza.location()
+                ))
+                .red(),
+            ),
+            Some(stanza) => lines.push(Line::from(format!(
+                "Priority: {priority} from {}",
+                stanza.location()
+            ))),
+            None if priority != DEFAULT_PRIORITY => lines.push(Line::from(format!(
+                "Priority: {priority}, its Release file asks not to install from it automatically"
+            ))),
+            None => {}
+        }
+        for stanza in self
+            .pin_view
+            .pins_of(source_ref)
+            .filter(|stanza| !stanza.is_general())
+        {
+            lines.push(Line::from(format!(
+                "Pin: {} to {} from {}",
+                stanza.packages_text(),
+                stanza.priority,
+                stanza.location()
+            )));
+        }
+        lines
+    }
+
+    /// Everything known about a source, shown under the list.
+    fn details(&self, source_ref: SourceRef) -> Vec<Line<'static>> {
+        let Some((apt_list, source)) = self.repositories.get(source_ref) else {
+            return Vec::new();
+        };
+        let mut lines = vec![Line::from(format!(
+            "{}:{}",
+            apt_list.file_path.display(),
+            source.line_number
+        ))
+        .bold()];
+        lines.push(Line::from(source.to_string()).dark_gray());
+        if let Some((vendor, _)) = self.vendor_of(source_ref) {
+            lines.push(Line::from(match &vendor.homepage {
+                Some(homepage) => format!("Vendor: {}, {homepage}", vendor.name),
+                None => format!("Vendor: {}", vendor.name),
+            }));
+        }
+        lines.extend(self.lists_details(source_ref));
+        if source.has_credentials() {
+            lines.push(
+                Line::from(format!(
+                    "Credentials: in the URI, readable by every user (C moves them to {AUTH_CONF_D_PATH})"
+                ))
+                .red(),
+            );
+        } else if let Some(entry) = self
+            .auth_entries
+            .iter()
+            .find(|entry| auth::machine_matches(&entry.machine, &source.uri))
+        {
+            lines.push(Line::from(format!(
+                "Credentials: {} from {}",
+                entry.login.as_deref().unwrap_or_default(),
+                entry.location()
+            )));
+        }
+        lines.extend(self.pin_details(source_ref));
+        for diagnostic in self.lint_view.diagnostics_of(&apt_list.file_path, source) {
+            let line = Line::from(format!(
+                "Lint, line {}: {}",
+                diagnostic.line.unwrap_or_default(),
+                diagnostic.message
+            ));
+            lines.push(match diagnostic.severity {
+                Severity::Error => line.red(),
+                Severity::Warning => line.yellow(),
+                Severity::Info => line,
+            });
+        }
+        for group in self
+            .duplicates
+            .iter()
+            .filter(|group| group.sources.contains(&source_ref))
+        {
+            let others: Vec<String> = group
+                .sources
+                .iter()
+                .filter(|other| **other != source_ref)
+                .map(|other| self.location(*other))
+                .collect();
+            lines.push(
+                Line::from(format!(
+                    "Duplicate: {} also configured in {}",
+                    group.components.join(" "),
+                    others.join(", ")
+                ))
+                .yellow(),
+            );
+        }
+        for (_, issue) in self
+            .suite_issues
+            .iter()
+            .filter(|(issue_ref, _)| *issue_ref == source_ref)
+        {
+            lines.push(Line::from(format!("Suite: {issue}")).yellow());
+        }
+        let audit = &self.keyring_view.audit;
+        if let Some(keyring) = audit.keyring_of(source_ref) {
+            let line = Line::from(format!("Keyring: {}", keyring.path.display()));
+            lines.push(match keyring.status {
+                KeyringStatus::Missing => line.red().add_modifier(Modifier::BOLD),
+                _ => line,
+            });
+            let now = openpgp::now();
+            if let Some(key) = keyring.longest_valid_key(now) {
+                let expiry = key.expiry(now);
+                let line = Line::from(format!(
+                    "Signed by {}, {}",
+                    key.name_without_email(),
+                    describe_expiry(expiry)
+                ));
+                lines.push(match expiry {
+                    KeyExpiry::Revoked | KeyExpiry::Expired(_) => line.red(),
+                    KeyExpiry::Soon(_) => line.yellow(),
+                    KeyExpiry::Valid(_) | KeyExpiry::Never => line,
+                });
+            } else if let Some(error) = &keyring.read_error {
+                lines.push(Line::from(format!("Unable to read the keyring: {error}")).yellow());
+            }
+        } else if audit.unsigned.contains(&source_ref) {
+            lines.push(
+                Line::from(format!(
+                    "Keyring: none, any key in {APT_TRUSTED_GPG_D_PATH} can sign it"
+                ))
+                .yellow(),
+            );
+        }
+        if let Some((vendor, Some(false))) = self.vendor_of(source_ref) {
+            lines.push(
+                Line::from(format!(
+                    "Key: apt doesn't accept the {} key from the vendor catalog for it ({}), check where the keyring came from",
+                    vendor.name,
+                    vendor.fingerprints.join(" or ")
+                ))
+                .red(),
+            );
+        }
+        lines
+    }
+
+    fn draw_status(&self, frame: &mut Frame, area: Rect) {
+        match &self.status {
+            Some(StatusMessage::Info(message)) => {
+                frame.render_widget(Paragraph::new(message.as_str()), area)
+            }
+            Some(StatusMessage::Error(message)) => frame.render_widget(
+                Paragraph::new(message.as_str()).style(Style::new().fg(Color::Red)),
+                area,
+            ),
+            None => {}
+        }
+    }
+
+    fn draw_table(&mut self, frame: &mut Frame, area: Rect) {
+        let header = Row::new([
+            "",
+            "Type",
+            "URI",
+            "Suite",
+            "Components",
+            "Options",
+            "File",
+            "Fetched",
+            "Flags",
+        ])
+        .style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self
+            .repositories
+            .source_refs()
+            .map(|(source_ref, apt_list, source)| {
+                let pinned_off = self.pin_view.is_pinned_off(source_ref);
+                let row = Row::new([
+                    Cell::from(match (source.enabled, pinned_off) {
+                        (true, true) => "-",
+                        (true, false) => "✓",
+                        (false, _) => " ",
+                    }),
+                    Cell::from(source.source_type.to_string()),
+                    Cell::from(match self.vendors.vendor_of(&source.uri) {
+                        Some(vendor) => Line::from(vec![
+                            Span::raw(vendor.name.clone()).bold(),
+                            Span::raw(" "),
+                            Span::raw(source.masked_uri()),
+                        ]),
+                        None => Line::from(source.masked_uri()),
+                    }),
+                    Cell::from(source.suite.clone()),
+                    Cell::from(source.components.join(" ")),
+                    Cell::from(source.options_display()),
+                    Cell::from(format!(
+                        "{}:{}",
+                        apt_list.display_name(),
+                        source.line_number
+                    )),
+                    Cell::from(
+                        self.lists
+                            .get(&source_ref)
+                            .and_then(|lists| lists.fetched)
+                            .map(format_date)
+                            .unwrap_or_default(),
+                    ),
+                    Cell::from(Line::from(self.badges(source_ref))),
+                ]);
+                if source.enabled && !pinned_off {
+                    row
+                } else {
+                    row.style(Style::new().fg(Color::DarkGray))
+                }
+            });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(1),
+                Constraint::Length(7),
+                Constraint::Fill(3),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Length(10),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(header)
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn draw_details(&self, frame: &mut Frame, area: Rect) {
+        let lines = match (self.view, self.selected_ref()) {
+            (AptView::Keyrings, _) => self.keyring_view.details(&self.repositories),
+            (AptView::Pins, _) => self.pin_view.details(&self.repositories),
+            (AptView::Lint, _) => self.lint_view.details(),
+            (AptView::Fixes, _) => self.fix_view.details(),
+            (AptView::Sources, Some(source_ref)) => self.details(source_ref),
+            (AptView::Sources, None) => {
+                vec![Line::from("Select a source to see its details").dark_gray()]
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().borders(Borders::TOP).title("Details"))
+                .wrap(Wrap { trim: false }),
+            area,
+        );
+    }
+}
+
 impl Component for PackageSources {
+    fn register_config_handler(&mut self, config: &Config) -> Result<()> {
+        self.config = config.clone();
+        self.load_settings();
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let Some((form, _)) = self.form.as_mut() else {
+            return Ok(None);
+        };
+        match key.code {
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                form.push_char(c)
+            }
+            KeyCode::Backspace => form.pop_char(),
+            _ => return Ok(None),
+        }
+        self.validate_form();
+        Ok(None)
+    }
+
     fn update(&mut self, action: Action, view_state: ViewState) -> Result<Option<Action>> {
         match action {
             Action::ListAction(list_action) => match view_state.mode {
                 Mode::PackageSourceTabs => self.handle_tab_movement(list_action),
                 Mode::PackageSourceList => self.handle_list_movement(list_action),
+                Mode::ConfirmationPopup if view_state.page == Page::PackageSources => {
+                    self.handle_popup_movement(list_action)
+                }
                 _ => Ok(None),
             },
+            Action::SourceAction(source_action) if view_state.mode == Mode::PackageSourceList => {
+                self.handle_source_action(source_action)
+            }
+            Action::NextField | Action::PrevField | Action::Confirm | Action::Cancel
+                if self.form.is_some() =>
+            {
+                self.handle_form_action(action)
+            }
+            Action::NextField | Action::PrevField if self.popup.is_some() => {
+                self.update_popup_cursor(action);
+                Ok(None)
+            }
+            Action::Confirm if self.popup.is_some() => self.confirm_popup(),
+            Action::Cancel if self.popup.is_some() => Ok(self.close_popup()),
             Action::NextMode => {
                 if view_state.mode == Mode::PackageSourceTabs {
                     Ok(Some(Action::UpdateViewState(ViewState::new(
@@ -195,15 +2109,77 @@ impl Component for PackageSources {
                 .divider(" ");
             frame.render_widget(tabs, centered);
 
+            if !self.is_enabled {
+                frame.render_widget(
+                    Paragraph::new("No apt sources found")
+                        .block(Block::bordered().borders(Borders::TOP)),
+                    page,
+                );
+                return Ok(());
+            }
+
             if !self.repositories.initialized {
                 self.repositories.load_repository_list()?;
+                self.os_release = OsRelease::load();
+                self.refresh_analysis();
             }
 
-            let list = List::new(self.repositories.get_repository_list())
-                .block(Block::bordered().borders(Borders::TOP))
-                .highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
-                .highlight_symbol(">");
-            frame.render_stateful_widget(list, page, &mut self.list_state);
+            let [views_area, page, details_area, status_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(10),
+                Constraint::Length(self.status.is_some() as u16),
+            ])
+            .areas(page);
+            let views = Tabs::new(AptView::ALL.map(AptView::title))
+                .highlight_style(Style::new().fg(Color::Green).add_modifier(Modifier::BOLD))
+                .select(AptView::ALL.iter().position(|view| *view == self.view))
+                .padding(" ", " ");
+            frame.render_widget(views, views_area);
+            let mut notes = Vec::new();
+            let errors = self.lint_view.count(Severity::Error);
+            let warnings = self.lint_view.count(Severity::Warning);
+            if errors + warnings > 0 {
+                notes.push(format!("{errors} lint errors, {warnings} warnings"));
+            }
+            let pin_problems =
+                self.pin_view.count(Severity::Error) + self.pin_view.count(Severity::Warning);
+            if pin_problems > 0 {
+                notes.push(format!("{pin_problems} pin problems"));
+            }
+            if self.vendors_unreadable {
+                notes.push(format!("{VENDORS_FILE} unreadable, see the log"));
+            }
+            if !self.fix_view.fixes.is_empty() {
+                notes.push(format!("{} fixes (F applies)", self.fix_view.fixes.len()));
+            }
+            if !self.orphaned_lists.files.is_empty() {
+                notes.push(format!(
+                    "{} orphaned list files, {} (x cleans)",
+                    self.orphaned_lists.files.len(),
+                    format_size(self.orphaned_lists.total_size())
+                ));
+            }
+            if !notes.is_empty() {
+                let note = format!("{} ", notes.join(" | "));
+                frame.render_widget(Paragraph::new(note).yellow().right_aligned(), views_area);
+            }
+            self.draw_status(frame, status_area);
+            match self.view {
+                AptView::Sources => self.draw_table(frame, page),
+                AptView::Keyrings => self.keyring_view.draw(frame, page, &self.repositories),
+                AptView::Pins => self.pin_view.draw(frame, page),
+                AptView::Lint => self.lint_view.draw(frame, page),
+                AptView::Fixes => self.fix_view.draw(frame, page),
+            }
+            self.draw_details(frame, details_area);
+
+            if let Some((popup, _)) = &self.popup {
+                popup.draw(frame, *area);
+            }
+            if let Some((form, _)) = &self.form {
+                form.draw(frame, *area);
+            }
         }
         Ok(())
     }
@@ -0,0 +1,120 @@
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame,
+};
+
+use crate::{
+    repositories::apt::{
+        auth::mask_credentials,
+        fixes::{DiffLine, Fix},
+        keyrings::KeyringAudit,
+        AptRepositories,
+    },
+    ui::action::ListAction,
+};
+
+/// A line of a fix preview, in the colors of a diff.
+pub fn diff_line(line: &DiffLine) -> Line<'static> {
+    match line {
+        DiffLine::Removed(number, text) => {
+            Line::from(format!("  {number:>4} - {}", mask_credentials(text))).red()
+        }
+        DiffLine::Added(number, text) => {
+            Line::from(format!("  {number:>4} + {}", mask_credentials(text))).green()
+        }
+        DiffLine::Renamed(from, to) => {
+            Line::from(format!("  {} -> {}", from.display(), to.display())).yellow()
+        }
+    }
+}
+
+/// The fixes view of the Package Sources page: changes that solve a lint problem on their own.
+#[derive(Default)]
+pub struct FixView {
+    pub fixes: Vec<Fix>,
+    table_state: TableState,
+}
+
+impl FixView {
+    pub fn refresh(&mut self, repositories: &AptRepositories, audit: &KeyringAudit) {
+        self.fixes = repositories.plan_fixes(audit);
+        if self
+            .table_state
+            .selected()
+            .is_some_and(|selected| selected >= self.fixes.len())
+        {
+            self.table_state.select(self.fixes.len().checked_sub(1));
+        }
+    }
+
+    pub fn selected_fix(&self) -> Option<&Fix> {
+        self.table_state
+            .selected()
+            .and_then(|selected| self.fixes.get(selected))
+    }
+
+    pub fn handle_list_movement(&mut self, list_action: ListAction) {
+        let last = self.fixes.len().saturating_sub(1);
+        let selected = self.table_state.selected();
+        match list_action {
+            ListAction::SelectNext => self.table_state.select(Some(match selected {
+                Some(selected) if selected < last => selected + 1,
+                _ => 0,
+            })),
+            ListAction::SelectPrev => self.table_state.select(Some(match selected {
+                Some(selected) if selected > 0 => selected - 1,
+                _ => last,
+            })),
+            ListAction::SelectFirst => self.table_state.select_first(),
+            ListAction::SelectLast => self.table_state.select(Some(last)),
+            ListAction::SelectNone => self.table_state.select(None),
+            ListAction::MakeSelection => {}
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.fixes.iter().map(|fix| {
+            Row::new([
+                Cell::from(fix.name()),
+                Cell::from(fix.path.display().to_string()),
+                Cell::from(fix.to_string()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Fill(2),
+                Constraint::Fill(3),
+            ],
+        )
+        .header(
+            Row::new(["Fix", "File", "Change"]).style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    /// The diff of the selected fix.
+    pub fn details(&self) -> Vec<Line<'static>> {
+        if self.fixes.is_empty() {
+            return vec![Line::from("Nothing to fix, z undoes the last fix").green()];
+        }
+        let Some(fix) = self.selected_fix() else {
+            return vec![
+                Line::from("Select a fix to see its diff, F applies all of them").dark_gray(),
+            ];
+        };
+        let mut lines = vec![Line::from(format!("{}: {fix}", fix.path.display())).bold()];
+        match fix.preview() {
+            Ok(diff) => lines.extend(diff.iter().map(diff_line)),
+            Err(e) => lines.push(Line::from(e.to_string()).red()),
+        }
+        lines
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame,
+};
+
+use crate::{
+    repositories::apt::{
+        keyrings::{
+            KeyringAudit, KeyringEntry, KeyringStatus, APT_TRUSTED_GPG_D_PATH,
+            LEGACY_TRUSTED_GPG_PATH,
+        },
+        openpgp::{self, format_date, KeyExpiry},
+        AptRepositories,
+    },
+    ui::action::ListAction,
+};
+
+use super::source_location;
+
+/// How a key's expiry reads in the details, e.g. `expires 2027-01-01`.
+pub fn describe_expiry(expiry: KeyExpiry) -> String {
+    match expiry {
+        KeyExpiry::Revoked => "revoked".to_string(),
+        KeyExpiry::Expired(expires) => format!("expired {}", format_date(expires)),
+        KeyExpiry::Soon(expires) | KeyExpiry::Valid(expires) => {
+            format!("expires {}", format_date(expires))
+        }
+        KeyExpiry::Never => "never expires".to_string(),
+    }
+}
+
+fn expiry_cell(keyring: &KeyringEntry, now: u64) -> Cell<'static> {
+    match keyring.expiry(now) {
+        Some(expiry @ (KeyExpiry::Revoked | KeyExpiry::Expired(_))) => {
+            Cell::from(describe_expiry(expiry)).red()
+        }
+        Some(expiry @ KeyExpiry::Soon(_)) => Cell::from(describe_expiry(expiry)).yellow(),
+        Some(KeyExpiry::Valid(expires)) => Cell::from(format_date(expires)),
+        Some(KeyExpiry::Never) => Cell::from("never"),
+        None if keyring.read_error.is_some() => Cell::from("unreadable").yellow(),
+        None => Cell::from(""),
+    }
+}
+
+/// The keyring audit of the Package Sources page: every keyring file, and the sources that
+/// don't name one.
+#[derive(Default)]
+pub struct KeyringView {
+    pub audit: KeyringAudit,
+    table_state: TableState,
+}
+
+impl KeyringView {
+    pub fn refresh(&mut self, repositories: &AptRepositories) {
+        self.audit = repositories.audit_keyrings();
+        if self
+            .table_state
+            .selected()
+            .is_some_and(|selected| selected >= self.row_count())
+        {
+            self.table_state.select(self.row_count().checked_sub(1));
+        }
+    }
+
+    fn row_count(&self) -> usize {
+        self.audit.keyrings.len() + self.audit.unsigned.len()
+    }
+
+    pub fn handle_list_movement(&mut self, list_action: ListAction) {
+        let last = self.row_count().saturating_sub(1);
+        let selected = self.table_state.selected();
+        match list_action {
+            ListAction::SelectNext => self.table_state.select(Some(match selected {
+                Some(selected) if selected < last => selected + 1,
+                _ => 0,
+            })),
+            ListAction::SelectPrev => self.table_state.select(Some(match selected {
+                Some(selected) if selected > 0 => selected - 1,
+                _ => last,
+            })),
+            ListAction::SelectFirst => self.table_state.select_first(),
+            ListAction::SelectLast => self.table_state.select(Some(last)),
+            ListAction::SelectNone => self.table_state.select(None),
+            ListAction::MakeSelection => {}
+        }
+    }
+
+    pub fn selected_keyring(&self) -> Option<&KeyringEntry> {
+        self.table_state
+            .selected()
+            .and_then(|selected| self.audit.keyrings.get(selected))
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, repositories: &AptRepositories) {
+        let used_by = |keyring: &KeyringEntry| {
+            let mut locations: Vec<String> = keyring
+                .used_by
+                .iter()
+                .map(|source_ref| source_location(repositories, *source_ref))
+                .collect();
+            // The sources of a deb822 stanza share a location
+            locations.dedup();
+            locations.join(", ")
+        };
+        let now = openpgp::now();
+        let keyring_rows = self.audit.keyrings.iter().map(|keyring| {
+            let status = Cell::from(keyring.status.to_string());
+            let status = match keyring.status {
+                KeyringStatus::Missing => status.red(),
+                KeyringStatus::Orphan => status.yellow(),
+                KeyringStatus::Global | KeyringStatus::Used => status,
+                KeyringStatus::Unused => status.dark_gray(),
+            };
+            let note = match keyring.status {
+                KeyringStatus::Global => "every source without signed-by".to_string(),
+                KeyringStatus::Unused => "nothing, removed along with its package".to_string(),
+                _ => used_by(keyring),
+            };
+            Row::new([
+                status,
+                Cell::from(keyring.path.display().to_string()),
+                expiry_cell(keyring, now),
+                Cell::from(keyring.package.clone().unwrap_or_default()),
+                Cell::from(note),
+            ])
+        });
+        let unsigned_rows = self.audit.unsigned.iter().map(|source_ref| {
+            let uri = repositories
+                .get(*source_ref)
+                .map(|(_, source)| source.masked_uri())
+                .unwrap_or_default();
+            Row::new([
+                Cell::from("unsigned").yellow(),
+                Cell::from(format!("any key in {APT_TRUSTED_GPG_D_PATH}")),
+                Cell::from(""),
+                Cell::from(""),
+                Cell::from(format!(
+                    "{} {uri}",
+                    source_location(repositories, *source_ref)
+                )),
+            ])
+        });
+
+        let table = Table::new(
+            keyring_rows.chain(unsigned_rows),
+            [
+                Constraint::Length(8),
+                Constraint::Fill(3),
+                Constraint::Length(18),
+                Constraint::Fill(1),
+                Constraint::Fill(3),
+            ],
+        )
+        .header(
+            Row::new(["Status", "Keyring", "Expires", "Package", "Used by"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    /// What the details pane shows for the selected row.
+    pub fn details(&self, repositories: &AptRepositories) -> Vec<Line<'static>> {
+        let Some(selected) = self.table_state.selected() else {
+            return vec![Line::from("Select a keyring to see its details").dark_gray()];
+        };
+        if let Some(keyring) = self.audit.keyrings.get(selected) {
+            let mut lines = vec![Line::from(keyring.path.display().to_string()).bold()];
+            lines.push(Line::from(match keyring.status {
+                KeyringStatus::Missing => "The file doesn't exist, apt will refuse these sources",
+                KeyringStatus::Orphan => {
+                    "No source uses this keyring and no package owns it, d deletes it"
+                }
+                KeyringStatus::Global if keyring.path == Path::new(LEGACY_TRUSTED_GPG_PATH) => {
+                    "Trusted for every source that has no signed-by, t moves its keys to keyrings of their own"
+                }
+                KeyringStatus::Global => "Trusted for every source that has no signed-by",
+                KeyringStatus::Used => "Used by the sources below",
+                KeyringStatus::Unused => "No source uses this keyring, but a package installed it",
+            }));
+            lines.extend(keyring.used_by.iter().map(|source_ref| {
+                Line::from(format!("  {}", source_location(repositories, *source_ref)))
+            }));
+            if let Some(error) = &keyring.read_error {
+                lines.push(Line::from(format!("Unable to read the keys: {error}")).yellow());
+            }
+            let now = openpgp::now();
+            for key in &keyring.keys {
+                let expiry = key.expiry(now);
+                let line = Line::from(format!(
+                    "Key {} {}, created {}, {}",
+                    key.fingerprint,
+                    key.name(),
+                    format_date(key.created),
+                    describe_expiry(expiry)
+                ));
+                lines.push(match expiry {
+                    KeyExpiry::Revoked | KeyExpiry::Expired(_) => line.red(),
+                    KeyExpiry::Soon(_) => line.yellow(),
+                    KeyExpiry::Valid(_) | KeyExpiry::Never => line,
+                });
+            }
+            return lines;
+        }
+        let unsigned = selected - self.audit.keyrings.len();
+        let Some(source_ref) = self.audit.unsigned.get(unsigned) else {
+            return Vec::new();
+        };
+        vec![
+            Line::from(source_location(repositories, *source_ref)).bold(),
+            Line::from(format!(
+                "Has no signed-by, so any key in {APT_TRUSTED_GPG_D_PATH} can sign it"
+            ))
+            .yellow(),
+        ]
+    }
+}
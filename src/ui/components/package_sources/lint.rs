@@ -0,0 +1,150 @@
+use std::fs;
+use std::path::Path;
+
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame,
+};
+
+use crate::{
+    repositories::apt::{
+        auth::mask_credentials,
+        lint::{self, Diagnostic, Severity},
+        AptSource,
+    },
+    ui::action::ListAction,
+};
+
+fn severity_cell(severity: Severity) -> Cell<'static> {
+    let cell = Cell::from(severity.to_string());
+    match severity {
+        Severity::Error => cell.red(),
+        Severity::Warning => cell.yellow(),
+        Severity::Info => cell.dark_gray(),
+    }
+}
+
+/// The lint view of the Package Sources page: every problem found in the source files, also
+/// printed by `--lint`.
+#[derive(Default)]
+pub struct LintView {
+    pub diagnostics: Vec<Diagnostic>,
+    table_state: TableState,
+}
+
+impl LintView {
+    pub fn refresh(&mut self) {
+        self.diagnostics = lint::lint();
+        if self
+            .table_state
+            .selected()
+            .is_some_and(|selected| selected >= self.diagnostics.len())
+        {
+            self.table_state
+                .select(self.diagnostics.len().checked_sub(1));
+        }
+    }
+
+    /// Diagnostics on the lines of `source`, which span a whole stanza in deb822 files.
+    pub fn diagnostics_of<'a>(
+        &'a self,
+        file_path: &'a Path,
+        source: &'a AptSource,
+    ) -> impl Iterator<Item = &'a Diagnostic> {
+        let lines = source.line_number..source.line_number + source.original.lines().count();
+        self.diagnostics.iter().filter(move |diagnostic| {
+            diagnostic.path == file_path
+                && diagnostic.line.is_some_and(|line| lines.contains(&line))
+        })
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    pub fn handle_list_movement(&mut self, list_action: ListAction) {
+        let last = self.diagnostics.len().saturating_sub(1);
+        let selected = self.table_state.selected();
+        match list_action {
+            ListAction::SelectNext => self.table_state.select(Some(match selected {
+                Some(selected) if selected < last => selected + 1,
+                _ => 0,
+            })),
+            ListAction::SelectPrev => self.table_state.select(Some(match selected {
+                Some(selected) if selected > 0 => selected - 1,
+                _ => last,
+            })),
+            ListAction::SelectFirst => self.table_state.select_first(),
+            ListAction::SelectLast => self.table_state.select(Some(last)),
+            ListAction::SelectNone => self.table_state.select(None),
+            ListAction::MakeSelection => {}
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.diagnostics.iter().map(|diagnostic| {
+            Row::new([
+                severity_cell(diagnostic.severity),
+                Cell::from(diagnostic.location()),
+                Cell::from(diagnostic.message.clone()),
+                Cell::from(diagnostic.code),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Fill(2),
+                Constraint::Fill(4),
+                Constraint::Length(20),
+            ],
+        )
+        .header(
+            Row::new(["Severity", "Location", "Problem", "Check"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    /// What the details pane shows for the selected diagnostic: the problem and the line it
+    /// is on.
+    pub fn details(&self) -> Vec<Line<'static>> {
+        if self.diagnostics.is_empty() {
+            return vec![Line::from("No problems found in the source files").green()];
+        }
+        let Some(diagnostic) = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.diagnostics.get(selected))
+        else {
+            return vec![Line::from("Select a problem to see its details").dark_gray()];
+        };
+        let mut lines = vec![
+            Line::from(diagnostic.location()).bold(),
+            Line::from(format!("{}: {}", diagnostic.severity, diagnostic.message)),
+        ];
+        let content = diagnostic.line.and_then(|line| {
+            Some(
+                fs::read_to_string(&diagnostic.path)
+                    .ok()?
+                    .lines()
+                    .nth(line - 1)?
+                    .to_string(),
+            )
+        });
+        if let Some(content) = content {
+            let content = mask_credentials(&content);
+            lines.push(Line::from(format!("  {content}")).dark_gray());
+        }
+        lines
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeSet, HashMap};
+
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Row, Table, TableState},
+    Frame,
+};
+
+use crate::{
+    repositories::apt::{
+        dpkg::InstalledPackage,
+        lint::{Diagnostic, Severity},
+        lists::{IndexCache, SourceLists},
+        preferences::{self, PinStanza, PinTarget},
+        AptRepositories, SourceRef,
+    },
+    ui::action::ListAction,
+};
+
+use super::source_location;
+
+fn priority_cell(priority: i32) -> Cell<'static> {
+    let cell = Cell::from(priority.to_string());
+    match priority {
+        ..0 => cell.red(),
+        1000.. => cell.yellow(),
+        _ => cell,
+    }
+}
+
+/// A line of the pins view: a stanza apt uses, or a problem that isn't on one.
+#[derive(Debug, Clone, Copy)]
+enum PinRow {
+    Stanza(usize),
+    Problem(usize),
+}
+
+/// The pins view of the Package Sources page: the stanzas of `/etc/apt/preferences` and
+/// `preferences.d`, the sources they apply to, and the ones that match nothing.
+#[derive(Default)]
+pub struct PinView {
+    pub stanzas: Vec<PinStanza>,
+    pub diagnostics: Vec<Diagnostic>,
+    targets: HashMap<SourceRef, PinTarget>,
+    rows: Vec<PinRow>,
+    table_state: TableState,
+}
+
+impl PinView {
+    pub fn refresh(
+        &mut self,
+        repositories: &AptRepositories,
+        lists: &HashMap<SourceRef, SourceLists>,
+        cache: &mut IndexCache,
+        installed: &[InstalledPackage],
+    ) {
+        let (stanzas, mut diagnostics) = preferences::read_preferences();
+        self.targets = repositories.pin_targets(lists);
+        let mut packages: BTreeSet<String> = installed
+            .iter()
+            .map(|package| package.name.clone())
+            .collect();
+        for index in self
+            .targets
+            .keys()
+            .filter_map(|source_ref| lists.get(source_ref))
+            .flat_map(|lists| &lists.indexes)
+        {
+            if let Ok(indexed) = cache.packages(index) {
+                packages.extend(indexed.iter().map(|package| package.name.clone()));
+            }
+        }
+        let targets: Vec<PinTarget> = self.targets.values().cloned().collect();
+        diagnostics.extend(preferences::lint_pins(&stanzas, &targets, &packages));
+        diagnostics.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+
+        self.rows = (0..stanzas.len()).map(PinRow::Stanza).collect();
+        self.rows.extend(
+            diagnostics
+                .iter()
+                .enumerate()
+                .filter(|(_, diagnostic)| {
+                    !stanzas.iter().any(|stanza| Self::is_on(diagnostic, stanza))
+                })
+                .map(|(index, _)| PinRow::Problem(index)),
+        );
+        self.stanzas = stanzas;
+        self.diagnostics = diagnostics;
+        if self
+            .table_state
+            .selected()
+            .is_some_and(|selected| selected >= self.rows.len())
+        {
+            self.table_state.select(self.rows.len().checked_sub(1));
+        }
+    }
+
+    fn is_on(diagnostic: &Diagnostic, stanza: &PinStanza) -> bool {
+        diagnostic.path == stanza.path && diagnostic.line == Some(stanza.line)
+    }
+
+    fn diagnostics_of<'a>(&'a self, stanza: &'a PinStanza) -> impl Iterator<Item = &'a Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(move |diagnostic| Self::is_on(diagnostic, stanza))
+    }
+
+    /// The enabled sources a stanza selects, in the order of the list.
+    fn sources_of(&self, stanza: &PinStanza) -> Vec<SourceRef> {
+        let mut sources: Vec<SourceRef> = self
+            .targets
+            .iter()
+            .filter(|(_, target)| stanza.matches_source(target))
+            .map(|(source_ref, _)| *source_ref)
+            .collect();
+        sources.sort();
+        sources
+    }
+
+    /// The stanzas that select `source_ref`, general or for some of its packages.
+    pub fn pins_of(&self, source_ref: SourceRef) -> impl Iterator<Item = &PinStanza> {
+        let target = self.targets.get(&source_ref);
+        self.stanzas
+            .iter()
+            .filter(move |stanza| target.is_some_and(|target| stanza.matches_source(target)))
+    }
+
+    /// The priority apt gives the packages of an enabled source, and the stanza that sets it.
+    pub fn priority_of(&self, source_ref: SourceRef) -> Option<(i32, Option<&PinStanza>)> {
+        let target = self.targets.get(&source_ref)?;
+        let (priority, stanza) = preferences::source_priority(&self.stanzas, target);
+        Some((priority, stanza.map(|index| &self.stanzas[index])))
+    }
+
+    /// Whether a pin below 0 keeps apt from installing anything from an enabled source.
+    pub fn is_pinned_off(&self, source_ref: SourceRef) -> bool {
+        self.priority_of(source_ref)
+            .is_some_and(|(priority, _)| priority < 0)
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    pub fn handle_list_movement(&mut self, list_action: ListAction) {
+        let last = self.rows.len().saturating_sub(1);
+        let selected = self.table_state.selected();
+        match list_action {
+            ListAction::SelectNext => self.table_state.select(Some(match selected {
+                Some(selected) if selected < last => selected + 1,
+                _ => 0,
+            })),
+            ListAction::SelectPrev => self.table_state.select(Some(match selected {
+                Some(selected) if selected > 0 => selected - 1,
+                _ => last,
+            })),
+            ListAction::SelectFirst => self.table_state.select_first(),
+            ListAction::SelectLast => self.table_state.select(Some(last)),
+            ListAction::SelectNone => self.table_state.select(None),
+            ListAction::MakeSelection => {}
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.rows.iter().map(|row| match *row {
+            PinRow::Stanza(index) => {
+                let stanza = &self.stanzas[index];
+                let worst = self
+                    .diagnostics_of(stanza)
+                    .map(|diagnostic| diagnostic.severity)
+                    .min();
+                let matches = match &stanza.pin {
+                    preferences::Pin::Version(_) => Cell::from("versions"),
+                    _ => match self.sources_of(stanza).len() {
+                        0 => Cell::from("nothing").yellow(),
+                        1 => Cell::from("1 source"),
+                        count => Cell::from(format!("{count} sources")),
+                    },
+                };
+                let location = Cell::from(stanza.location());
+                Row::new([
+                    match worst {
+                        Some(Severity::Error) => location.red(),
+                        Some(Severity::Warning) => location.yellow(),
+                        _ => location,
+                    },
+                    Cell::from(stanza.packages_text()),
+                    Cell::from(stanza.pin_text.clone()),
+                    priority_cell(stanza.priority),
+                    matches,
+                ])
+            }
+            PinRow::Problem(index) => {
+                let diagnostic = &self.diagnostics[index];
+                let location = Cell::from(diagnostic.location());
+                Row::new([
+                    match diagnostic.severity {
+                        Severity::Error => location.red(),
+                        _ => location.yellow(),
+                    },
+                    Cell::from(""),
+                    Cell::from(diagnostic.message.clone()),
+                ])
+                .style(Style::new().fg(Color::DarkGray))
+            }
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Fill(3),
+                Constraint::Length(8),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new(["Location", "Package", "Pin", "Priority", "Matches"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    /// The sources the selected stanza applies to and its problems.
+    pub fn details(&self, repositories: &AptRepositories) -> Vec<Line<'static>> {
+        if self.rows.is_empty() {
+            return vec![Line::from("No pins, every source has priority 500").green()];
+        }
+        let Some(row) = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.rows.get(selected))
+        else {
+            return vec![Line::from("Select a pin to see the sources it applies to").dark_gray()];
+        };
+        let (location, diagnostics): (String, Vec<&Diagnostic>) = match *row {
+            PinRow::Stanza(index) => {
+                let stanza = &self.stanzas[index];
+                (stanza.location(), self.diagnostics_of(stanza).collect())
+            }
+            PinRow::Problem(index) => {
+                let diagnostic = &self.diagnostics[index];
+                (diagnostic.location(), vec![diagnostic])
+            }
+        };
+        let mut lines = vec![Line::from(location).bold()];
+        if let PinRow::Stanza(index) = *row {
+            let stanza = &self.stanzas[index];
+            lines.push(Line::from(stanza.to_string()));
+            let sources = self.sources_of(stanza);
+            if !sources.is_empty() {
+                let locations: Vec<String> = sources
+                    .iter()
+                    .map(|source_ref| source_location(repositories, *source_ref))
+                    .collect();
+                lines.push(Line::from(format!("Applies to {}", locations.join(", "))));
+            }
+            // An earlier stanza may set the priority of some of the sources instead
+            let disabled = sources
+                .iter()
+                .filter(|source_ref| {
+                    self.priority_of(**source_ref)
+                        .is_some_and(|(priority, by)| priority < 0 && by == Some(stanza))
+                })
+                .count();
+            if disabled > 0 {
+                lines.push(
+                    Line::from(format!(
+                        "apt installs nothing from {disabled} of them, as if they were disabled"
+                    ))
+                    .red(),
+                );
+            }
+        }
+        for diagnostic in diagnostics {
+            let line = Line::from(format!("{}: {}", diagnostic.severity, diagnostic.message));
+            lines.push(match diagnostic.severity {
+                Severity::Error => line.red(),
+                Severity::Warning => line.yellow(),
+                Severity::Info => line,
+            });
+        }
+        lines
+    }
+}
@@ -0,0 +1,70 @@
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// A scrollable box drawn over a page, used to show a change before it is applied.
+#[derive(Debug, Default, Clone)]
+pub struct Popup {
+    title: String,
+    lines: Vec<Line<'static>>,
+    footer: String,
+    scroll: u16,
+}
+
+impl Popup {
+    pub fn new(title: impl Into<String>, lines: Vec<Line<'static>>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            footer: " y: confirm  n: cancel ".to_string(),
+            scroll: 0,
+        }
+    }
+
+    pub fn with_footer(mut self, footer: impl Into<String>) -> Self {
+        self.footer = footer.into();
+        self
+    }
+
+    /// Replaces the content, e.g. after the user changed what the popup is about to do.
+    pub fn set_lines(&mut self, lines: Vec<Line<'static>>) {
+        self.lines = lines;
+    }
+
+    pub fn scroll_to(&mut self, line: usize) {
+        self.scroll = line.min(self.lines.len().saturating_sub(1)) as u16;
+    }
+
+    pub fn scroll_down(&mut self) {
+        if (self.scroll as usize) < self.lines.len().saturating_sub(1) {
+            self.scroll += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        let [area] = Layout::vertical([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::bordered()
+            .title(self.title.as_str())
+            .title_bottom(self.footer.as_str())
+            .border_style(Style::default().fg(Color::Yellow));
+        let paragraph = Paragraph::new(self.lines.clone())
+            .block(block)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(Clear, area);
+        frame.render_widget(paragraph, area);
+    }
+}
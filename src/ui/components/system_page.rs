@@ -2,48 +2,263 @@ use std::collections::HashMap;
 
 use color_eyre::Result;
 use ratatui::{
-    layout::Rect,
-    style::{Color, Style},
-    widgets::Block,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
     Frame,
 };
 use tracing::info;
 
-use crate::ui::{action::Action, Mode, Page, ViewState};
+use crate::{
+    repositories::{
+        apt::{
+            permissions::{self, PermissionFinding, Risk},
+            AptRepositories,
+        },
+        Repository,
+    },
+    ui::{
+        action::{Action, ListAction, SystemAction},
+        Mode, Page, ViewState,
+    },
+};
+
+use super::{popup::Popup, Component};
+
+fn risk_cell(risk: Risk) -> Cell<'static> {
+    let cell = Cell::from(risk.to_string());
+    match risk {
+        Risk::Critical => cell.red().add_modifier(Modifier::BOLD),
+        Risk::High => cell.red(),
+        Risk::Medium => cell.yellow(),
+        Risk::Low => cell.dark_gray(),
+    }
+}
 
-use super::Component;
+/// Outcome of the last change made from this page, shown under the list.
+enum StatusMessage {
+    Info(String),
+    Error(String),
+}
 
+/// The system page: what others than root can change in the apt configuration.
 #[derive(Default)]
 pub struct SystemPage {
-    focused: bool,
+    repositories: AptRepositories,
+    findings: Vec<PermissionFinding>,
+    table_state: TableState,
+    status: Option<StatusMessage>,
+    /// The findings the open popup is about to fix.
+    popup: Option<(Popup, Vec<PermissionFinding>)>,
 }
 
 impl SystemPage {
     pub fn new() -> Self {
-        Self {
-            // The system page is selected by default
-            focused: false,
+        Self::default()
+    }
+
+    fn refresh(&mut self) {
+        if let Err(e) = self.repositories.load_repository_list() {
+            self.status = Some(StatusMessage::Error(e.to_string()));
+        }
+        self.findings = self.repositories.audit_permissions();
+        if self
+            .table_state
+            .selected()
+            .is_some_and(|selected| selected >= self.findings.len())
+        {
+            self.table_state.select(self.findings.len().checked_sub(1));
         }
     }
+
+    fn handle_list_movement(&mut self, list_action: ListAction) {
+        self.status = None;
+        let last = self.findings.len().saturating_sub(1);
+        let selected = self.table_state.selected();
+        match list_action {
+            ListAction::SelectNext => self.table_state.select(Some(match selected {
+                Some(selected) if selected < last => selected + 1,
+                _ => 0,
+            })),
+            ListAction::SelectPrev => self.table_state.select(Some(match selected {
+                Some(selected) if selected > 0 => selected - 1,
+                _ => last,
+            })),
+            ListAction::SelectFirst => self.table_state.select_first(),
+            ListAction::SelectLast => self.table_state.select(Some(last)),
+            ListAction::SelectNone => self.table_state.select(None),
+            ListAction::MakeSelection => {}
+        }
+    }
+
+    fn handle_system_action(&mut self, system_action: SystemAction) -> Option<Action> {
+        self.status = None;
+        let findings: Vec<PermissionFinding> = match system_action {
+            SystemAction::Refresh => {
+                self.refresh();
+                return None;
+            }
+            SystemAction::FixPermission => self
+                .table_state
+                .selected()
+                .and_then(|selected| self.findings.get(selected))
+                .into_iter()
+                .filter(|finding| finding.fix.is_some())
+                .cloned()
+                .collect(),
+            SystemAction::FixAllPermissions => self
+                .findings
+                .iter()
+                .filter(|finding| finding.fix.is_some())
+                .cloned()
+                .collect(),
+        };
+        if findings.is_empty() {
+            self.status = Some(StatusMessage::Info("Nothing that can be fixed".to_string()));
+            return None;
+        }
+        if !permissions::is_privileged() {
+            self.status = Some(StatusMessage::Error(
+                "Changing modes and owners needs root, run yacufu with sudo".to_string(),
+            ));
+            return None;
+        }
+        let mut lines = vec![
+            Line::from(match findings.len() {
+                1 => "Apply this fix?".to_string(),
+                count => format!("Apply these {count} fixes?"),
+            })
+            .bold(),
+            Line::default(),
+        ];
+        lines.extend(findings.iter().map(|finding| {
+            let fix = finding.fix.map(|fix| fix.to_string()).unwrap_or_default();
+            Line::from(format!("  {fix:<12} {}", finding.path.display()))
+        }));
+        self.popup = Some((Popup::new(" Fix permissions ", lines), findings));
+        Some(Action::UpdateViewState(ViewState::new(
+            Mode::ConfirmationPopup,
+            Page::System,
+        )))
+    }
+
+    fn close_popup(&mut self) -> Option<Action> {
+        self.popup = None;
+        Some(Action::UpdateViewState(ViewState::new(
+            Mode::System,
+            Page::System,
+        )))
+    }
+
+    fn confirm_popup(&mut self) -> Option<Action> {
+        let (_, findings) = self.popup.take()?;
+        let result = permissions::fix_permissions(&findings);
+        self.refresh();
+        self.status = Some(match result {
+            Ok(count) => StatusMessage::Info(format!("Fixed {count} permission problems")),
+            Err(e) => StatusMessage::Error(e.to_string()),
+        });
+        self.close_popup()
+    }
+
+    fn draw_table(&mut self, frame: &mut Frame, area: Rect) {
+        let rows = self.findings.iter().map(|finding| {
+            Row::new([
+                risk_cell(finding.risk),
+                Cell::from(finding.path.display().to_string()),
+                Cell::from(finding.message.clone()),
+                Cell::from(finding.fix.map(|fix| fix.to_string()).unwrap_or_default()),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Fill(2),
+                Constraint::Fill(3),
+                Constraint::Length(12),
+            ],
+        )
+        .header(
+            Row::new(["Risk", "Path", "Problem", "Fix"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().borders(Borders::TOP))
+        .row_highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
+        .highlight_symbol(">");
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
+    fn details(&self) -> Vec<Line<'static>> {
+        if self.findings.is_empty() {
+            return vec![Line::from("Only root can change what apt reads").green()];
+        }
+        let Some(finding) = self
+            .table_state
+            .selected()
+            .and_then(|selected| self.findings.get(selected))
+        else {
+            return vec![Line::from("Select a finding to see its details").dark_gray()];
+        };
+        let mut lines = vec![
+            Line::from(finding.path.display().to_string()).bold(),
+            Line::from(format!(
+                "{}: {} [{}]",
+                finding.risk, finding.message, finding.code
+            )),
+        ];
+        lines.push(match finding.fix {
+            Some(fix) => Line::from(format!("Fix: {fix} (f applies it, F applies all)")),
+            None => Line::from("No fix that is right in every case, check it by hand").dark_gray(),
+        });
+        lines
+    }
+
+    /// Counts of the findings by risk, for the title line.
+    fn summary(&self) -> String {
+        let mut counts = Vec::new();
+        for risk in [Risk::Critical, Risk::High, Risk::Medium, Risk::Low] {
+            let count = self
+                .findings
+                .iter()
+                .filter(|finding| finding.risk == risk)
+                .count();
+            if count > 0 {
+                counts.push(format!("{count} {risk}"));
+            }
+        }
+        if !permissions::is_privileged() {
+            counts.push("fixes need root".to_string());
+        }
+        counts.join(" | ")
+    }
 }
 
 impl Component for SystemPage {
     fn update(&mut self, action: Action, view_state: ViewState) -> Result<Option<Action>> {
         match action {
-            Action::ListAction(list_action) => {
-                if view_state.mode == Mode::System {
+            Action::ListAction(list_action) => match view_state.mode {
+                Mode::System => {
                     info!("SystemPage handling action: {list_action:?}");
-                    match list_action {
-                        // ListAction::SelectNext => self.state.select_next(),
-                        // ListAction::SelectPrev => self.state.select_previous(),
-                        // ListAction::SelectFirst => self.state.select_first(),
-                        // ListAction::SelectLast => self.state.select_last(),
-                        // ListAction::SelectNone => self.state.select(None),
-                        // ListAction::MarkSelection => todo!(),
-                        _ => {}
+                    self.handle_list_movement(list_action);
+                }
+                Mode::ConfirmationPopup if view_state.page == Page::System => {
+                    if let Some((popup, _)) = self.popup.as_mut() {
+                        match list_action {
+                            ListAction::SelectNext => popup.scroll_down(),
+                            ListAction::SelectPrev => popup.scroll_up(),
+                            _ => {}
+                        }
                     }
                 }
+                _ => {}
+            },
+            Action::SystemAction(system_action) if view_state.mode == Mode::System => {
+                return Ok(self.handle_system_action(system_action));
             }
+            Action::Confirm if self.popup.is_some() => return Ok(self.confirm_popup()),
+            Action::Cancel if self.popup.is_some() => return Ok(self.close_popup()),
             _ => {}
         }
         Ok(None)
@@ -57,42 +272,57 @@ impl Component for SystemPage {
     ) -> Result<()> {
         if view_state.page == Page::System {
             let area = areas.get("page").unwrap();
-            let border_style = match self.focused {
-                true => Style::default().fg(Color::Blue),
-                false => Style::default(),
+            let border_style = match view_state.mode {
+                Mode::System => Style::default().fg(Color::Blue),
+                _ => Style::default(),
             };
             let block = Block::bordered().title("System").border_style(border_style);
-            // let inner = block.inner(*area);
-
+            let inner = block.inner(*area);
             frame.render_widget(block, *area);
 
-            // let [tabs_area, page] =
-            //     Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).areas(inner);
-            // let [_, centered, _] =
-            //     Layout::horizontal([Constraint::Fill(1), Constraint::Min(0), Constraint::Fill(1)])
-            //         .flex(Flex::Center)
-            //         .areas(tabs_area);
-
-            // let tabs = Tabs::new(vec!["Apt", "Flatpak", "Homebrew"])
-            //     .highlight_style(
-            //         Style::new()
-            //             .fg(Color::Green)
-            //             .add_modifier(Modifier::UNDERLINED),
-            //     )
-            //     .select(0)
-            //     .padding("", "")
-            //     .divider(" ");
-            // frame.render_widget(tabs, centered);
-
-            // if !self.repositories.initialized {
-            //     self.repositories.load_repository_list()?;
-            // }
-
-            // let list = List::new(self.repositories.get_repository_list())
-            //     .block(Block::bordered().borders(Borders::TOP))
-            //     .highlight_style(Style::new().bg(Color::Blue).add_modifier(Modifier::BOLD))
-            //     .highlight_symbol(">");
-            // frame.render_stateful_widget(list, page, &mut self.state);
+            if !self.repositories.check_for_repository() {
+                frame.render_widget(Paragraph::new("No apt configuration found"), inner);
+                return Ok(());
+            }
+            if !self.repositories.initialized {
+                self.refresh();
+            }
+
+            let [title_area, table_area, details_area, status_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(4),
+                Constraint::Length(self.status.is_some() as u16),
+            ])
+            .areas(inner);
+            frame.render_widget(
+                Paragraph::new(" Permissions of the apt configuration").bold(),
+                title_area,
+            );
+            frame.render_widget(
+                Paragraph::new(format!("{} ", self.summary()))
+                    .yellow()
+                    .right_aligned(),
+                title_area,
+            );
+            self.draw_table(frame, table_area);
+            frame.render_widget(
+                Paragraph::new(self.details()).block(Block::bordered().borders(Borders::TOP)),
+                details_area,
+            );
+            match &self.status {
+                Some(StatusMessage::Info(message)) => {
+                    frame.render_widget(Paragraph::new(message.as_str()), status_area)
+                }
+                Some(StatusMessage::Error(message)) => {
+                    frame.render_widget(Paragraph::new(message.as_str()).red(), status_area)
+                }
+                None => {}
+            }
+
+            if let Some((popup, _)) = &self.popup {
+                popup.draw(frame, *area);
+            }
         }
         Ok(())
     }